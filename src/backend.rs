@@ -0,0 +1,48 @@
+use crate::compiler_explorer::{CompilationResult, CompileOptions, CompilerExplorerClient, Error};
+
+use std::future::Future;
+use std::pin::Pin;
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Turns source code into a `CompilationResult`, be it remotely or on this machine.
+pub trait Backend: Send + Sync {
+    /// Identifies the backend, results of different backends must never be mixed up.
+    fn id(&self) -> String;
+
+    fn compile<'a>(
+        &'a self,
+        compiler: &'a str,
+        source: &'a str,
+        options: &'a CompileOptions,
+    ) -> BoxFuture<'a, Result<CompilationResult, Error>>;
+
+    /// Instruction set targeted by `compiler`, in the naming of the compiler explorer
+    /// instruction documentation (`amd64`, `aarch64`, ...).
+    fn instruction_set<'a>(&'a self, compiler: &'a str) -> BoxFuture<'a, Result<String, Error>>;
+}
+
+impl Backend for CompilerExplorerClient {
+    fn id(&self) -> String {
+        self.base_url().to_owned()
+    }
+
+    fn compile<'a>(
+        &'a self,
+        compiler: &'a str,
+        source: &'a str,
+        options: &'a CompileOptions,
+    ) -> BoxFuture<'a, Result<CompilationResult, Error>> {
+        Box::pin(CompilerExplorerClient::compile(
+            self, compiler, source, options,
+        ))
+    }
+
+    fn instruction_set<'a>(&'a self, compiler: &'a str) -> BoxFuture<'a, Result<String, Error>> {
+        Box::pin(async move {
+            CompilerExplorerClient::instruction_set(self, compiler)
+                .await?
+                .ok_or_else(|| Error::UnknownInstructionSet(compiler.to_owned()))
+        })
+    }
+}
@@ -0,0 +1,178 @@
+use crate::compiler_explorer::{CompilationResult, CompileOptions};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+pub const DEFAULT_MAX_SIZE: u64 = 64 * 1024 * 1024;
+
+const ENTRY_EXTENSION: &str = "json";
+
+/// Numbers the temporary files of the writers of this process.
+static NEXT_TMP: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Serialize, Deserialize)]
+struct Entry<T> {
+    /// Seconds since the unix epoch
+    created: u64,
+    result: T,
+}
+
+/// On-disk cache of compilation results and instruction documentation.
+///
+/// Entries are keyed by everything that affects the output of a compilation, so re-saving an
+/// unchanged file or going back to a previous configuration does not hit the network. Failures
+/// are logged and treated as cache misses, the cache is never a reason to fail a compilation.
+#[derive(Clone, Debug)]
+pub struct Cache {
+    dir: PathBuf,
+    ttl: Duration,
+    max_size: u64,
+}
+
+impl Cache {
+    /// Creates a cache in the application data directory.
+    pub fn new() -> Option<Self> {
+        let dir = dirs_next::data_dir()?.join(".ce_cache");
+        Some(Self::with_dir(dir, DEFAULT_TTL, DEFAULT_MAX_SIZE))
+    }
+
+    pub fn with_dir(dir: PathBuf, ttl: Duration, max_size: u64) -> Self {
+        Self { dir, ttl, max_size }
+    }
+
+    /// Builds the key for a compilation. `backend` identifies who performs the compilation, for
+    /// instance the URL of the compiler explorer instance.
+    pub fn key(backend: &str, compiler: &str, source: &str, options: &CompileOptions) -> String {
+        // Serializing the options makes new options part of the key automatically
+        digest(&("compilation", backend, compiler, source, options))
+    }
+
+    /// Builds the key for the documentation of `opcode`, which only depends on the instance it
+    /// was fetched from.
+    pub fn docs_key(backend: &str, instruction_set: &str, opcode: &str) -> String {
+        digest(&("asm-docs", backend, instruction_set, opcode.to_lowercase()))
+    }
+
+    pub fn get(&self, key: &str) -> Option<CompilationResult> {
+        self.get_value(key)
+    }
+
+    pub fn put(&self, key: &str, result: &CompilationResult) {
+        self.put_value(key, result)
+    }
+
+    /// Like `get`, for anything else stored in the cache.
+    pub fn get_value<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let path = self.entry_path(key);
+        let data = std::fs::read(&path).ok()?;
+        let entry: Entry<T> = match serde_json::from_slice(&data) {
+            Ok(entry) => entry,
+            Err(e) => {
+                ::log::warn!("Discarding invalid cache entry {:?}: {}", path, e);
+                let _ = std::fs::remove_file(&path);
+                return None;
+            }
+        };
+
+        if now().saturating_sub(entry.created) > self.ttl.as_secs() {
+            ::log::debug!("Cache entry {} expired", key);
+            let _ = std::fs::remove_file(&path);
+            return None;
+        }
+
+        ::log::debug!("Cache hit for {}", key);
+        Some(entry.result)
+    }
+
+    pub fn put_value<T: Serialize>(&self, key: &str, result: &T) {
+        if let Err(e) = self.try_put(key, result) {
+            ::log::warn!("Unable to store cache entry {}: {}", key, e);
+        }
+        if let Err(e) = self.prune() {
+            ::log::warn!("Unable to prune cache: {}", e);
+        }
+    }
+
+    fn try_put<T: Serialize>(&self, key: &str, result: &T) -> Result<(), std::io::Error> {
+        std::fs::create_dir_all(&self.dir)?;
+        let entry = Entry {
+            created: now(),
+            result,
+        };
+        let data = serde_json::to_vec(&entry)?;
+
+        // Write to a temporary file first so that readers never see partial entries. Every writer
+        // has its own, other processes or threads may store the same entry concurrently
+        let path = self.entry_path(key);
+        let tmp_path = self.dir.join(format!(
+            "{}.{}-{}.tmp",
+            key,
+            std::process::id(),
+            NEXT_TMP.fetch_add(1, Ordering::Relaxed)
+        ));
+        let result = std::fs::write(&tmp_path, data).and_then(|_| std::fs::rename(&tmp_path, path));
+        if result.is_err() {
+            let _ = std::fs::remove_file(&tmp_path);
+        }
+        result
+    }
+
+    /// Removes expired entries and then the oldest ones until the cache fits in `max_size`.
+    fn prune(&self) -> Result<(), std::io::Error> {
+        let mut entries = vec![];
+        for dir_entry in std::fs::read_dir(&self.dir)? {
+            let dir_entry = dir_entry?;
+            let path = dir_entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(ENTRY_EXTENSION) {
+                continue;
+            }
+            let metadata = dir_entry.metadata()?;
+            let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+            entries.push((modified, metadata.len(), path));
+        }
+
+        let expiry = SystemTime::now()
+            .checked_sub(self.ttl)
+            .unwrap_or(UNIX_EPOCH);
+        entries.sort();
+
+        let mut total_size: u64 = entries.iter().map(|(_, size, _)| size).sum();
+        for (modified, size, path) in entries {
+            if modified >= expiry && total_size <= self.max_size {
+                break;
+            }
+            ::log::debug!("Evicting cache entry {:?}", path);
+            std::fs::remove_file(path)?;
+            total_size -= size;
+        }
+        Ok(())
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(key).with_extension(ENTRY_EXTENSION)
+    }
+}
+
+/// SHA-256 of the JSON serialization of `value`, which unlike `Hash` stays the same across Rust
+/// releases so entries outlive upgrades. Struct fields always serialize in declaration order.
+fn digest<T: Serialize>(value: &T) -> String {
+    let data = serde_json::to_vec(value).unwrap_or_default();
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default()
+}
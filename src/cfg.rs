@@ -0,0 +1,369 @@
+//! Control flow graphs built from the assembly returned by any backend.
+//!
+//! Functions start at global labels and are split into basic blocks at local labels, jump targets
+//! and after jumps and returns. Both gcc/clang assembly (where jumps refer to labels) and objdump
+//! disassembly (where jumps refer to addresses) are understood.
+
+use crate::compiler_explorer::AsmOutput;
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    /// Label the block starts at, if any
+    pub label: Option<String>,
+    /// Instructions, without indentation
+    pub lines: Vec<String>,
+    /// Indices of the blocks control can continue to, the fallthrough block first
+    pub successors: Vec<usize>,
+}
+
+impl BasicBlock {
+    /// Label of the block, or a name made up from its index.
+    pub fn name(&self, index: usize) -> String {
+        self.label.clone().unwrap_or_else(|| format!("B{}", index))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Function {
+    pub name: String,
+    pub blocks: Vec<BasicBlock>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Target {
+    Label(String),
+    Address(u64),
+}
+
+/// How an instruction affects control flow.
+#[derive(Debug, PartialEq)]
+enum Flow {
+    Next,
+    Jump {
+        conditional: bool,
+        target: Option<Target>,
+    },
+    Return,
+}
+
+struct Instruction {
+    label: Option<String>,
+    text: String,
+    address: Option<u64>,
+    flow: Flow,
+}
+
+/// Builds the control flow graph of every function in `asm`.
+pub fn build(asm: &[AsmOutput]) -> Vec<Function> {
+    let mut functions = vec![];
+    let mut current: Option<(String, Vec<Instruction>)> = None;
+    let mut pending_label = None;
+
+    for line in asm {
+        let text = line.text.trim();
+        if text.is_empty() || is_directive(text) {
+            continue;
+        }
+
+        if !line.text.starts_with(char::is_whitespace) && text.ends_with(':') {
+            let label = text.trim_end_matches(':').to_owned();
+            if label.starts_with('.') {
+                pending_label = Some(label);
+            } else {
+                functions.extend(current.take().map(|(name, body)| split(name, body)));
+                current = Some((label, vec![]));
+                pending_label = None;
+            }
+            continue;
+        }
+
+        if let Some((_, body)) = &mut current {
+            body.push(Instruction {
+                label: pending_label.take(),
+                text: text.to_owned(),
+                address: line.address,
+                flow: flow(text),
+            });
+        }
+    }
+    functions.extend(current.map(|(name, body)| split(name, body)));
+    functions
+}
+
+fn split(name: String, body: Vec<Instruction>) -> Function {
+    let addresses: HashMap<u64, usize> = body
+        .iter()
+        .enumerate()
+        .filter_map(|(index, instruction)| instruction.address.map(|address| (address, index)))
+        .collect();
+    let labels: HashMap<&str, usize> = body
+        .iter()
+        .enumerate()
+        .filter_map(|(index, instruction)| instruction.label.as_deref().map(|label| (label, index)))
+        .collect();
+    let target_index = |target: &Target| match target {
+        Target::Label(label) => labels.get(label.as_str()).copied(),
+        Target::Address(address) => addresses.get(address).copied(),
+    };
+
+    // Instructions that start a basic block
+    let mut leaders = vec![false; body.len()];
+    for (index, instruction) in body.iter().enumerate() {
+        if index == 0 || instruction.label.is_some() {
+            leaders[index] = true;
+        }
+        match &instruction.flow {
+            Flow::Next => {}
+            Flow::Jump { target, .. } => {
+                if let Some(target) = target.as_ref().and_then(target_index) {
+                    leaders[target] = true;
+                }
+                if index + 1 < body.len() {
+                    leaders[index + 1] = true;
+                }
+            }
+            Flow::Return => {
+                if index + 1 < body.len() {
+                    leaders[index + 1] = true;
+                }
+            }
+        }
+    }
+
+    let starts: Vec<usize> = (0..body.len()).filter(|index| leaders[*index]).collect();
+    let block_of = |instruction: usize| starts.partition_point(|start| *start <= instruction) - 1;
+
+    let blocks = starts
+        .iter()
+        .enumerate()
+        .map(|(block, start)| {
+            let end = starts.get(block + 1).copied().unwrap_or(body.len());
+            let instructions = &body[*start..end];
+            let mut successors = vec![];
+            match &instructions.last().unwrap().flow {
+                Flow::Next => {
+                    if end < body.len() {
+                        successors.push(block + 1);
+                    }
+                }
+                Flow::Jump {
+                    conditional,
+                    target,
+                } => {
+                    if *conditional && end < body.len() {
+                        successors.push(block + 1);
+                    }
+                    if let Some(target) = target.as_ref().and_then(target_index) {
+                        let target = block_of(target);
+                        if !successors.contains(&target) {
+                            successors.push(target);
+                        }
+                    }
+                }
+                Flow::Return => {}
+            }
+            BasicBlock {
+                // The entry block is known by the name of the function
+                label: match block {
+                    0 => body[*start].label.clone().or_else(|| Some(name.clone())),
+                    _ => body[*start].label.clone(),
+                },
+                lines: instructions
+                    .iter()
+                    .map(|instruction| instruction.text.clone())
+                    .collect(),
+                successors,
+            }
+        })
+        .collect();
+
+    Function { name, blocks }
+}
+
+fn is_directive(text: &str) -> bool {
+    text.starts_with('.') && !text.ends_with(':')
+}
+
+fn flow(text: &str) -> Flow {
+    let (mnemonic, operands) = match text.split_once(char::is_whitespace) {
+        Some((mnemonic, operands)) => (mnemonic, operands.trim()),
+        None => (text, ""),
+    };
+    let mnemonic = mnemonic.to_ascii_lowercase();
+
+    let unconditional = matches!(mnemonic.as_str(), "jmp" | "jmpq" | "b" | "j");
+    let conditional = !unconditional
+        && (
+            // x86, but not the RISC-V calls and returns
+            (mnemonic.starts_with('j')
+                && !matches!(mnemonic.as_str(), "jmp" | "jal" | "jalr" | "jr"))
+            // ARM, AArch64 and RISC-V
+            || mnemonic.starts_with("b.")
+            || matches!(
+                mnemonic.as_str(),
+                "beq" | "bne" | "bcs" | "bhs" | "bcc" | "blo" | "bmi" | "bpl" | "bvs" | "bvc"
+                    | "bhi" | "bls" | "bge" | "blt" | "bgt" | "ble" | "cbz" | "cbnz" | "tbz"
+                    | "tbnz"
+            )
+            || matches!(
+                mnemonic.as_str(),
+                "beqz" | "bnez" | "blez" | "bgez" | "bltz" | "bgtz" | "bltu" | "bgeu"
+            )
+        );
+    if unconditional || conditional {
+        return Flow::Jump {
+            conditional,
+            target: target(operands),
+        };
+    }
+
+    match mnemonic.as_str() {
+        "ret" | "retq" | "retl" => Flow::Return,
+        "bx" if operands == "lr" => Flow::Return,
+        // RISC-V returns are `jr ra`, other registers are indirect tail calls
+        "jr" => Flow::Return,
+        _ => Flow::Next,
+    }
+}
+
+/// Parses the destination of a jump, `None` for indirect jumps.
+fn target(operands: &str) -> Option<Target> {
+    // Drop comments, e.g. `jmp .L3 # loop`
+    let operands = operands.split(['#', ';']).next().unwrap_or_default();
+
+    // objdump shows the address followed by the symbol, e.g. `1150 <main+0x17>`. The symbol may
+    // contain commas when demangled
+    if let Some((operands, _)) = operands.split_once('<') {
+        let address = operands.rsplit(',').next()?.trim();
+        return u64::from_str_radix(address, 16).ok().map(Target::Address);
+    }
+
+    // The destination is the last operand, e.g. `cbz w0, .L3`
+    let operand = operands.rsplit(',').next()?.trim();
+    if operand.is_empty()
+        || operand.contains(['[', '(', '%', '*'])
+        || operand.chars().all(|c| c.is_ascii_alphanumeric()) && !operand.starts_with('.')
+    {
+        // Registers and memory operands
+        return None;
+    }
+    Some(Target::Label(operand.to_owned()))
+}
+
+/// Draws the blocks of `function` as boxes, one below the other. Successors that are not the next
+/// block are listed under each box, and loops are drawn as arrows in the left margin going from the
+/// end of a block back to the top of the block it jumps to.
+pub fn render(function: &Function) -> Vec<String> {
+    let blocks = &function.blocks;
+    let names: Vec<String> = blocks
+        .iter()
+        .enumerate()
+        .map(|(index, block)| block.name(index))
+        .collect();
+
+    // Every back edge gets its own column in the margin
+    let back_edges: Vec<(usize, usize)> = blocks
+        .iter()
+        .enumerate()
+        .flat_map(|(from, block)| {
+            block
+                .successors
+                .iter()
+                .filter(move |to| **to <= from)
+                .map(move |to| (from, *to))
+        })
+        .collect();
+    let margin = back_edges.len() * 2;
+
+    let width = blocks
+        .iter()
+        .zip(&names)
+        .flat_map(|(block, name)| {
+            block
+                .lines
+                .iter()
+                .map(|line| line.chars().count())
+                .chain(std::iter::once(name.chars().count() + 2))
+        })
+        .max()
+        .unwrap_or(0)
+        + 2;
+
+    // Rows are built without the margin first, remembering where back edges start and end
+    let mut rows: Vec<String> = vec![];
+    let mut block_top = vec![0; blocks.len()];
+    let mut block_bottom = vec![0; blocks.len()];
+    for (index, block) in blocks.iter().enumerate() {
+        block_top[index] = rows.len();
+        let title = format!(" {} ", names[index]);
+        rows.push(format!(
+            "┌─{}{}┐",
+            title,
+            "─".repeat(width - title.chars().count() - 1)
+        ));
+        for line in &block.lines {
+            rows.push(format!(
+                "│ {}{}│",
+                line,
+                " ".repeat(width - line.chars().count() - 1)
+            ));
+        }
+        rows.push(format!("└{}┘", "─".repeat(width)));
+
+        let jumps: Vec<String> = block
+            .successors
+            .iter()
+            .filter(|to| **to != index + 1)
+            .map(|to| {
+                if *to <= index {
+                    format!("↑ {}", names[*to])
+                } else {
+                    format!("↓ {}", names[*to])
+                }
+            })
+            .collect();
+        if !jumps.is_empty() {
+            rows.push(format!("  {}", jumps.join("  ")));
+        }
+        block_bottom[index] = rows.len() - 1;
+    }
+
+    if margin == 0 {
+        return rows;
+    }
+
+    let mut gutter = vec![vec![' '; margin]; rows.len()];
+    for (edge, (from, to)) in back_edges.iter().enumerate() {
+        let column = edge * 2;
+        let (start, end) = (block_top[*to], block_bottom[*from]);
+        for (row, cells) in gutter.iter_mut().enumerate().take(end + 1).skip(start) {
+            if row == start || row == end {
+                // Another edge may already go through this row
+                cells[column] = match (row == start, cells[column]) {
+                    (true, '─') => '┬',
+                    (true, _) => '┌',
+                    (false, '─') => '┴',
+                    (false, _) => '└',
+                };
+                for cell in cells.iter_mut().skip(column + 1) {
+                    *cell = match *cell {
+                        '│' | '┼' => '┼',
+                        _ => '─',
+                    };
+                }
+                if row == start {
+                    cells[margin - 1] = '►';
+                }
+            } else if cells[column] == ' ' || cells[column] == '─' {
+                cells[column] = if cells[column] == '─' { '┼' } else { '│' };
+            }
+        }
+    }
+
+    rows.into_iter()
+        .zip(gutter)
+        .map(|(row, cells)| cells.into_iter().collect::<String>() + &row)
+        .collect()
+}
@@ -0,0 +1,52 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Terminals drop OSC 52 sequences above a size limit, around 100kB for xterm and tmux.
+const MAX_OSC52_LEN: usize = 100_000;
+
+/// Where `copy` put the text.
+pub enum Destination {
+    Clipboard,
+    File(PathBuf),
+}
+
+/// Copies `text` to the system clipboard with the OSC 52 escape sequence, which the terminal
+/// handles, so it also works over SSH without a clipboard daemon. Text too large for the
+/// sequence is written to a file only the user can read, in their runtime or data directory.
+pub fn copy(text: &str) -> Result<Destination, std::io::Error> {
+    let encoded = base64::encode(text);
+    if encoded.len() <= MAX_OSC52_LEN {
+        match write_osc52(&encoded) {
+            Ok(()) => return Ok(Destination::Clipboard),
+            Err(e) => ::log::warn!("Unable to write to the clipboard: {}", e),
+        }
+    }
+
+    let dir = dirs_next::runtime_dir()
+        .or_else(dirs_next::data_dir)
+        .ok_or_else(|| std::io::Error::other("No runtime or data directory to write to"))?;
+    let path = dir.join("ce-yank.txt");
+    // Replace the previous yank rather than writing through it, it may have become a symlink
+    match std::fs::remove_file(&path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(&path)?.write_all(text.as_bytes())?;
+    Ok(Destination::File(path))
+}
+
+fn write_osc52(encoded: &str) -> Result<(), std::io::Error> {
+    let mut sequence = format!("\x1b]52;c;{}\x07", encoded);
+    // tmux only forwards sequences wrapped in its passthrough one, with escapes doubled
+    if std::env::var_os("TMUX").is_some() {
+        sequence = format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"));
+    }
+
+    let mut stdout = std::io::stdout();
+    stdout.write_all(sequence.as_bytes())?;
+    stdout.flush()
+}
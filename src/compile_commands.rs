@@ -0,0 +1,420 @@
+//! Flags of a file from the `compile_commands.json` of its build, as CMake, Meson and Bear
+//! write it.
+
+use crate::compiler_explorer::SourceFile;
+
+use serde_derive::Deserialize;
+
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+
+/// Flags that only say which files to write or how to print diagnostics, without changing the
+/// code generated
+const DROPPED_FLAGS: &[&str] = &[
+    "-c",
+    "-S",
+    "-E",
+    "-M",
+    "-MM",
+    "-MD",
+    "-MMD",
+    "-MP",
+    "-MG",
+    "-pipe",
+    "-fsyntax-only",
+    "-fcolor-diagnostics",
+    "-fno-color-diagnostics",
+];
+/// Like `DROPPED_FLAGS`, taking a value either joined or as the next argument
+const DROPPED_VALUE_FLAGS: &[&str] = &["-o", "-MF", "-MT", "-MQ"];
+/// Flags taking the next argument as their value, which is kept with them. Values of flags
+/// missing here are still kept unless they look like flags themselves.
+const VALUE_FLAGS: &[&str] = &[
+    "-D",
+    "-U",
+    "-x",
+    "-target",
+    "-arch",
+    "--param",
+    "-Xclang",
+    "-mllvm",
+    "-Xpreprocessor",
+    "-Xassembler",
+    "-Xlinker",
+    "-Xanalyzer",
+    "-Xopenmp-target",
+    "-Xcuda-ptxas",
+];
+/// Like `VALUE_FLAGS`, for flags that pass the value on to a tool, e.g. `-Xarch_x86_64 -O3`
+const VALUE_FLAG_PREFIXES: &[&str] = &["-Xarch_"];
+/// Flags taking a path after an `=`, relative to the directory of the entry
+const JOINED_PATH_FLAGS: &[&str] = &[
+    "-fprofile-use",
+    "-fprofile-instr-use",
+    "-fprofile-sample-use",
+    "-fprofile-list",
+    "-fsanitize-blacklist",
+    "-fsanitize-ignorelist",
+];
+/// Flags rewriting paths of this machine in the output, as `-fdebug-prefix-map=old=new`
+const PREFIX_MAP_FLAGS: &[&str] = &[
+    "-fdebug-prefix-map",
+    "-ffile-prefix-map",
+    "-fmacro-prefix-map",
+    "-fprofile-prefix-map",
+    "-fcoverage-prefix-map",
+];
+/// Directories searched for headers
+const INCLUDE_FLAGS: &[&str] = &["-isystem", "-iquote", "-idirafter", "-I"];
+/// Headers included before the source
+const FORCED_INCLUDE_FLAG: &str = "-include";
+/// Other flags taking a path, relative to the directory of the entry. Longer flags come first
+/// as the path may be joined to the flag.
+const PATH_FLAGS: &[&str] = &[
+    "-include-pch",
+    FORCED_INCLUDE_FLAG,
+    "-imacros",
+    "-ivfsoverlay",
+    "-isysroot",
+    "--sysroot",
+    "-L",
+    "-B",
+];
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Unable to read {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    #[error("Invalid compilation database {0}: {1}")]
+    Parse(PathBuf, serde_json::Error),
+    #[error("No entry for {0} in {1}")]
+    NotFound(PathBuf, PathBuf),
+}
+
+/// How a file is compiled. Either `arguments` or `command` is set.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Entry {
+    /// Working directory of the compilation, which relative paths are relative to
+    pub directory: PathBuf,
+    pub file: PathBuf,
+    #[serde(default)]
+    pub arguments: Vec<String>,
+    /// The arguments as a single shell command
+    #[serde(default)]
+    pub command: Option<String>,
+}
+
+/// Flags of an entry, ready to compile its file somewhere else.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Flags {
+    pub arguments: Vec<String>,
+    /// Directories searched for headers that were left out of `arguments`, absolute
+    pub include_dirs: Vec<PathBuf>,
+    /// Headers included with `-include` that were left out of `arguments`, absolute
+    pub forced_includes: Vec<PathBuf>,
+}
+
+/// Headers to send along with a source.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Bundle {
+    pub files: Vec<SourceFile>,
+    /// Names of the forced includes among `files`, in order, to pass to `-include`
+    pub forced_includes: Vec<String>,
+    /// Headers found that cannot be named next to the source, like `../config.h`, as they are
+    /// written in the `#include`
+    pub skipped: Vec<String>,
+}
+
+impl Entry {
+    /// The compiler invocation, starting with the compiler.
+    pub fn arguments(&self) -> Vec<String> {
+        match &self.command {
+            Some(command) if self.arguments.is_empty() => split_command(command),
+            _ => self.arguments.clone(),
+        }
+    }
+
+    /// Translates the arguments into flags for compiling the file elsewhere. The compiler, the
+    /// file and the flags that only change which files are written are dropped.
+    ///
+    /// Paths only exist on this machine, so with `local` they are made absolute to keep working
+    /// from another directory. Otherwise they are dropped, and the include directories and
+    /// forced includes are returned so their headers can be sent along with the source.
+    pub fn flags(&self, local: bool) -> Flags {
+        let mut flags = Flags::default();
+        let arguments = self.arguments();
+        let mut arguments = arguments.iter().skip(1);
+        // Whether the last argument was a flag kept as is, which may take the next one as value
+        let mut after_kept_flag = false;
+        while let Some(argument) = arguments.next() {
+            let follows_kept_flag = std::mem::take(&mut after_kept_flag);
+            if DROPPED_FLAGS.contains(&argument.as_str())
+                || argument.starts_with("-fdiagnostics-color")
+                || argument.starts_with("-save-temps")
+            {
+                continue;
+            }
+            if !argument.starts_with('-') {
+                if follows_kept_flag && !self.is_file(argument) {
+                    flags.arguments.push(argument.clone());
+                }
+                continue;
+            }
+            if VALUE_FLAGS.contains(&argument.as_str())
+                || VALUE_FLAG_PREFIXES
+                    .iter()
+                    .any(|prefix| argument.starts_with(prefix))
+            {
+                flags.arguments.push(argument.clone());
+                flags.arguments.extend(arguments.next().cloned());
+                continue;
+            }
+            if let Some((_, value)) = split_flag(argument, DROPPED_VALUE_FLAGS) {
+                if value.is_empty() {
+                    arguments.next();
+                }
+                continue;
+            }
+            if let Some((flag, value)) = argument.split_once('=') {
+                if JOINED_PATH_FLAGS.contains(&flag) {
+                    if local {
+                        let path = self.directory.join(value);
+                        flags.arguments.push(format!("{}={}", flag, path.display()));
+                    }
+                    continue;
+                }
+                if PREFIX_MAP_FLAGS.contains(&flag) {
+                    if local {
+                        flags.arguments.push(argument.clone());
+                    }
+                    continue;
+                }
+            }
+
+            // `-Bstatic` and `-Bdynamic` are linker flags, not `-B` joined to a directory
+            let path_flag = split_flag(argument, PATH_FLAGS).filter(|(flag, value)| {
+                *flag != "-B" || value.is_empty() || value.contains('/') || value.starts_with('.')
+            });
+            let (flag, value, is_include) = match split_flag(argument, INCLUDE_FLAGS) {
+                Some((flag, value)) => (flag, value, true),
+                None => match path_flag {
+                    Some((flag, value)) => (flag, value, false),
+                    None => {
+                        flags.arguments.push(argument.clone());
+                        after_kept_flag = true;
+                        continue;
+                    }
+                },
+            };
+            let value = match value {
+                "" => match arguments.next() {
+                    Some(value) => value.as_str(),
+                    None => break,
+                },
+                value => value,
+            };
+            let path = self.directory.join(value);
+            if local {
+                let separator = if flag.starts_with("--") { "=" } else { "" };
+                flags
+                    .arguments
+                    .push(format!("{}{}{}", flag, separator, path.display()));
+            } else if is_include {
+                flags.include_dirs.push(path);
+            } else if flag == FORCED_INCLUDE_FLAG {
+                flags.forced_includes.push(path);
+            }
+        }
+        flags
+    }
+
+    /// Whether `argument` names the file being compiled.
+    fn is_file(&self, argument: &str) -> bool {
+        let resolve = |path: &Path| {
+            let mut resolved = PathBuf::new();
+            for component in self.directory.join(path).components() {
+                match component {
+                    Component::CurDir => {}
+                    Component::ParentDir => {
+                        resolved.pop();
+                    }
+                    component => resolved.push(component),
+                }
+            }
+            resolved
+        };
+        resolve(Path::new(argument)) == resolve(&self.file)
+    }
+}
+
+/// Loads the compilation database at `database` and finds the entry that compiles `file`.
+pub fn lookup(database: &Path, file: &Path) -> Result<Entry, Error> {
+    let contents =
+        std::fs::read_to_string(database).map_err(|e| Error::Io(database.to_owned(), e))?;
+    let entries: Vec<Entry> =
+        serde_json::from_str(&contents).map_err(|e| Error::Parse(database.to_owned(), e))?;
+
+    let canonical = |path: &Path| std::fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+    let file = canonical(file);
+    entries
+        .into_iter()
+        .find(|entry| canonical(&entry.directory.join(&entry.file)) == file)
+        .ok_or_else(|| Error::NotFound(file, database.to_owned()))
+}
+
+/// Finds the headers `source` includes from `source_dir` or `include_dirs`, and those they
+/// include in turn. Each one is named after its path from the directory it was found in, so
+/// that it is found again next to the source when given `-I.`. System headers are left out.
+///
+/// `forced_includes` are bundled too, named after their file name, or put in a directory of
+/// their own when another one has the same name.
+pub fn bundle_headers(
+    source: &str,
+    source_dir: &Path,
+    include_dirs: &[PathBuf],
+    forced_includes: &[PathBuf],
+) -> Bundle {
+    let mut bundle = Bundle::default();
+    let mut seen = HashSet::new();
+    let parent = |path: &Path| path.parent().unwrap_or(Path::new("")).to_owned();
+    // Contents to scan, with the directory of the file and its name among the bundled ones
+    let mut pending = vec![(source.to_owned(), source_dir.to_owned(), PathBuf::new())];
+    for (index, path) in forced_includes.iter().enumerate() {
+        let (file_name, contents) = match (path.file_name(), std::fs::read_to_string(path)) {
+            (Some(file_name), Ok(contents)) => (Path::new(file_name), contents),
+            _ => {
+                bundle.skipped.push(path.display().to_string());
+                continue;
+            }
+        };
+        let canonical = std::fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+        if !seen.insert(canonical) {
+            continue;
+        }
+        let taken = bundle
+            .forced_includes
+            .iter()
+            .any(|name| Path::new(name) == file_name);
+        let name = if taken {
+            Path::new(&format!("include-{}", index)).join(file_name)
+        } else {
+            file_name.to_owned()
+        };
+        pending.push((contents.clone(), parent(path), parent(&name)));
+        let name = name.to_string_lossy().into_owned();
+        bundle.forced_includes.push(name.clone());
+        bundle.files.push(SourceFile {
+            filename: name,
+            contents,
+        });
+    }
+    while let Some((contents, dir, name_dir)) = pending.pop() {
+        for (header, quoted) in contents.lines().filter_map(parse_include) {
+            // Quoted includes are looked up next to the file first
+            let candidates = quoted
+                .then_some((dir.as_path(), name_dir.as_path()))
+                .into_iter()
+                .chain(
+                    include_dirs
+                        .iter()
+                        .map(|dir| (dir.as_path(), Path::new(""))),
+                );
+            for (dir, name_dir) in candidates {
+                let path = dir.join(header);
+                if !path.is_file() {
+                    continue;
+                }
+                let canonical = std::fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+                if !seen.insert(canonical) {
+                    break;
+                }
+                // Files can only be written next to the source, not above it
+                let name = match normalize(&name_dir.join(header)) {
+                    Some(name) => name,
+                    None => {
+                        bundle.skipped.push(header.to_owned());
+                        break;
+                    }
+                };
+                if let Ok(contents) = std::fs::read_to_string(&path) {
+                    pending.push((contents.clone(), parent(&path), parent(&name)));
+                    bundle.files.push(SourceFile {
+                        filename: name.to_string_lossy().into_owned(),
+                        contents,
+                    });
+                }
+                break;
+            }
+        }
+    }
+    bundle
+}
+
+/// The header of an `#include` line, and whether it is quoted rather than in angle brackets.
+fn parse_include(line: &str) -> Option<(&str, bool)> {
+    let rest = line.trim_start().strip_prefix('#')?.trim_start();
+    let rest = rest.strip_prefix("include")?.trim_start();
+    let (close, quoted) = match rest.chars().next()? {
+        '"' => ('"', true),
+        '<' => ('>', false),
+        _ => return None,
+    };
+    let header = rest[1..].split(close).next()?;
+    (!header.is_empty()).then_some((header, quoted))
+}
+
+/// Resolves `.` and `..` in a relative path, or returns `None` if it leaves its root.
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => normalized.push(part),
+            Component::CurDir => {}
+            Component::ParentDir if normalized.pop() => {}
+            _ => return None,
+        }
+    }
+    Some(normalized)
+}
+
+/// The flag among `flags` that `argument` is, with its value when joined to it.
+fn split_flag<'a>(argument: &'a str, flags: &[&'static str]) -> Option<(&'static str, &'a str)> {
+    flags.iter().find_map(|flag| {
+        let value = argument.strip_prefix(flag)?;
+        match value.strip_prefix('=') {
+            Some(value) if flag.starts_with("--") => Some((*flag, value)),
+            _ if flag.starts_with("--") && !value.is_empty() => None,
+            _ => Some((*flag, value)),
+        }
+    })
+}
+
+/// Splits a command the way a POSIX shell would, for the quoting compilation databases use.
+fn split_command(command: &str) -> Vec<String> {
+    let mut arguments = vec![];
+    let mut current: Option<String> = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => arguments.extend(current.take()),
+            '\'' => {
+                let current = current.get_or_insert_with(String::new);
+                current.extend(chars.by_ref().take_while(|c| *c != '\''));
+            }
+            '"' => {
+                let current = current.get_or_insert_with(String::new);
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => current.extend(chars.next()),
+                        c => current.push(c),
+                    }
+                }
+            }
+            '\\' => current.get_or_insert_with(String::new).extend(chars.next()),
+            c => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+    arguments.extend(current);
+    arguments
+}
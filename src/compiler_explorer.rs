@@ -1,31 +1,340 @@
+use serde_derive::{Deserialize, Serialize};
 use serde_json::value::Value;
 
-#[derive(Debug, Clone)]
+use std::time::Duration;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExecutionResult {
     pub code: i64,
     pub stdout: Vec<StreamOutput>,
     pub stderr: Vec<StreamOutput>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CompilationResult {
     pub code: i64,
     pub stdout: Vec<StreamOutput>,
     pub stderr: Vec<StreamOutput>,
     pub asm: Vec<AsmOutput>,
     pub execution: Option<ExecutionResult>,
+    /// LLVM IR, only present when requested with `ProduceOptions::ir`
+    #[serde(default)]
+    pub ir: Option<Vec<AsmOutput>>,
+    /// AST dump, only present when requested with `ProduceOptions::ast`
+    #[serde(default)]
+    pub ast: Option<Vec<AsmOutput>>,
+    /// GCC tree/RTL dumps, only present when requested with `ProduceOptions::gcc_dump`
+    #[serde(default)]
+    pub gcc_dump: Option<GccDump>,
+    /// Optimization pipeline, only present when requested with `ProduceOptions::opt_pipeline`
+    #[serde(default)]
+    pub opt_pipeline: Option<Vec<OptPipelineFunction>>,
+    /// Optimization remarks, only present when requested with `ProduceOptions::opt_info`
+    #[serde(default)]
+    pub opt_remarks: Option<Vec<OptRemark>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StreamOutput {
     pub text: String,
     pub tag: Option<(i64, String)>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AsmOutput {
     pub text: String,
     pub source: Option<(Option<String>, i64)>,
+    /// Only present when disassembling binaries
+    #[serde(default)]
+    pub address: Option<u64>,
+    /// Encoded instruction bytes in hex, only present when disassembling binaries
+    #[serde(default)]
+    pub opcodes: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GccDumpPass {
+    pub name: String,
+    pub command_prefix: String,
+    pub filename_suffix: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GccDump {
+    /// Every pass that produced a dump
+    pub passes: Vec<GccDumpPass>,
+    pub selected_pass: Option<GccDumpPass>,
+    /// Dump of the selected pass
+    pub output: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OptPipelinePass {
+    pub name: String,
+    /// Machine passes work on MIR instead of LLVM IR
+    pub machine: bool,
+    pub changed: bool,
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OptPipelineFunction {
+    pub name: String,
+    pub passes: Vec<OptPipelinePass>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RemarkKind {
+    /// An optimization was applied
+    Passed,
+    /// An optimization was considered but not applied
+    Missed,
+    Analysis,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OptRemark {
+    pub kind: RemarkKind,
+    /// Pass that emitted the remark, e.g. `inline` or `loop-vectorize`. Empty when unknown
+    pub pass: String,
+    pub function: Option<String>,
+    /// Line and column in the source
+    pub location: Option<(i64, i64)>,
+    pub message: String,
+}
+
+/// Documentation of an instruction, as returned by `/api/asm/{instructionSet}/{opcode}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstructionDocs {
+    /// One paragraph summary
+    pub tooltip: String,
+    pub html: String,
+    /// Reference the documentation was taken from
+    pub url: Option<String>,
+}
+
+impl InstructionDocs {
+    /// Full documentation as plain text, falling back to the tooltip.
+    pub fn text(&self) -> String {
+        if self.html.is_empty() {
+            return self.tooltip.clone();
+        }
+        strip_html(&self.html)
+    }
+}
+
+/// A compiler offered by an instance, as listed by `/api/compilers`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Compiler {
+    pub id: String,
+    pub name: String,
+    /// Id of the language it compiles
+    pub lang: String,
+    /// Instruction set of the generated code, `None` for those documentation is not available for
+    #[serde(default)]
+    pub instruction_set: Option<String>,
+}
+
+/// A language supported by an instance, as listed by `/api/languages`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Language {
+    pub id: String,
+    pub name: String,
+    /// Extensions of its source files, with the leading dot
+    #[serde(default)]
+    pub extensions: Vec<String>,
+}
+
+/// Removes the markup from `html`, keeping block elements on lines of their own.
+pub fn strip_html(html: &str) -> String {
+    let mut text = String::new();
+    let mut tag: Option<String> = None;
+    for c in html.chars() {
+        match (&mut tag, c) {
+            (None, '<') => tag = Some(String::new()),
+            (Some(name), '>') => {
+                let name = name.trim_start_matches('/').to_lowercase();
+                let name = name.split(|c: char| c.is_whitespace() || c == '/').next();
+                if matches!(
+                    name,
+                    Some("br" | "p" | "div" | "li" | "tr" | "pre" | "table" | "h1" | "h2" | "h3")
+                ) {
+                    text.push('\n');
+                }
+                tag = None;
+            }
+            (Some(name), c) => name.push(c),
+            (None, c) => text.push(c),
+        }
+    }
+
+    let text = text
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&");
+
+    // Collapse the blank lines left by nested block elements
+    let mut result = String::new();
+    let mut blank = 0;
+    for line in text.lines().map(str::trim_end) {
+        if line.trim().is_empty() {
+            blank += 1;
+            continue;
+        }
+        if !result.is_empty() {
+            result.push_str(if blank > 0 { "\n\n" } else { "\n" });
+        }
+        result.push_str(line);
+        blank = 0;
+    }
+    result
+}
+
+/// Output filters applied by the compiler explorer instance to the generated assembly.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Filters {
+    /// Disassemble the linked binary instead of showing the compiler output
+    pub binary: bool,
+    /// Disassemble the object file instead of showing the compiler output
+    pub binary_object: bool,
+    pub labels: bool,
+    pub directives: bool,
+    pub comment_only: bool,
+    pub demangle: bool,
+    pub intel: bool,
+    pub library_code: bool,
+    pub trim: bool,
+}
+
+impl Default for Filters {
+    /// Same defaults as the compiler explorer web UI.
+    fn default() -> Self {
+        Self {
+            binary: false,
+            binary_object: false,
+            labels: true,
+            directives: true,
+            comment_only: true,
+            demangle: true,
+            intel: true,
+            library_code: true,
+            trim: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Library {
+    pub id: String,
+    pub version: String,
+}
+
+impl std::str::FromStr for Library {
+    type Err = String;
+
+    /// Parses libraries given as `id:version`, e.g. `fmt:trunk`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some((id, version)) if !id.is_empty() && !version.is_empty() => Ok(Self {
+                id: id.to_owned(),
+                version: version.to_owned(),
+            }),
+            _ => Err(format!("Invalid library `{}`, expected id:version", s)),
+        }
+    }
+}
+
+/// Outputs the compiler produces besides the assembly. Each of them makes compilations slower, so
+/// only the ones being displayed should be requested.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProduceOptions {
+    pub ir: bool,
+    pub ast: bool,
+    pub gcc_dump: bool,
+    /// Pass to dump, compiler explorer only returns the list of passes when unset
+    pub gcc_dump_pass: Option<GccDumpPass>,
+    pub opt_pipeline: bool,
+    pub opt_info: bool,
+}
+
+impl ProduceOptions {
+    /// Builds the `compilerOptions` of a compile request.
+    fn compiler_options(&self) -> Value {
+        let mut options = serde_json::Map::new();
+        if self.ir {
+            options.insert(
+                "produceIr".to_owned(),
+                serde_json::json!({
+                    "filterDebugInfo": true,
+                    "filterIRMetadata": true,
+                    "filterAttributes": true,
+                    "filterComments": true,
+                    "noDiscardValueNames": true,
+                    "demangle": true,
+                }),
+            );
+        }
+        if self.ast {
+            options.insert("produceAst".to_owned(), Value::Bool(true));
+        }
+        if self.gcc_dump {
+            options.insert(
+                "produceGccDump".to_owned(),
+                serde_json::json!({
+                    "opened": true,
+                    "pass": self.gcc_dump_pass,
+                    "treeDump": true,
+                    "rtlDump": true,
+                    "ipaDump": true,
+                    "dumpFlags": {},
+                }),
+            );
+        }
+        if self.opt_pipeline {
+            options.insert(
+                "produceOptPipeline".to_owned(),
+                serde_json::json!({
+                    "fullModule": false,
+                    "filterDebugInfo": true,
+                    "filterIRMetadata": true,
+                    "noDiscardValueNames": true,
+                    "demangle": true,
+                    "libraryFunctions": false,
+                }),
+            );
+        }
+        if self.opt_info {
+            options.insert("produceOptInfo".to_owned(), Value::Bool(true));
+        }
+        Value::Object(options)
+    }
+}
+
+/// Everything besides the source and the compiler that affects a compilation.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompileOptions {
+    pub arguments: Vec<String>,
+    pub filters: Filters,
+    pub libraries: Vec<Library>,
+    pub execute: bool,
+    pub produce: ProduceOptions,
+    /// Headers sent along with the source. Local toolchains find them where they are.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<SourceFile>,
+}
+
+/// A file written next to the source before compiling it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceFile {
+    /// Path relative to the source
+    pub filename: String,
+    pub contents: String,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -40,13 +349,28 @@ pub enum Error {
     InvalidAsm,
     #[error("Error parsing error code")]
     InvalidErrorCode,
-    #[error("Invalid HTTP response: {0}")]
-    InvalidHttpResponse(String),
+    #[error("Invalid HTTP response ({0}): {1}")]
+    InvalidHttpResponse(reqwest::StatusCode, String),
     #[error("HTTP Error: {0}")]
     HttpError(#[from] reqwest::Error),
+    #[error("Unable to run {0}: {1}")]
+    Process(String, std::io::Error),
+    #[error("IO Error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Error parsing compiler list")]
+    InvalidCompilerList,
+    #[error("Error parsing language list")]
+    InvalidLanguageList,
+    #[error("No link in the shortener response")]
+    InvalidShortLink,
+    #[error("Unable to find the instruction set of {0}")]
+    UnknownInstructionSet(String),
+    #[error("No documentation found for {0}")]
+    UnknownOpcode(String),
 }
 
-fn parse_asm(json_array: &Value) -> Result<Vec<AsmOutput>, Error> {
+/// Parses the `asm` array of a compilation result.
+pub fn parse_asm(json_array: &Value) -> Result<Vec<AsmOutput>, Error> {
     match json_array {
         Value::Array(array) => {
             let mut result = vec![];
@@ -54,15 +378,34 @@ fn parse_asm(json_array: &Value) -> Result<Vec<AsmOutput>, Error> {
                 let text = node["text"].as_str().ok_or(Error::InvalidAsm)?.to_owned();
                 let source = match &node["source"] {
                     Value::Object(source_map) => {
-                        let file = source_map["file"].as_str().map(|x| x.to_owned());
-                        let line = source_map["line"].as_i64().ok_or(Error::InvalidAsm)?;
+                        let file = source_map
+                            .get("file")
+                            .and_then(Value::as_str)
+                            .map(|x| x.to_owned());
+                        let line = source_map
+                            .get("line")
+                            .and_then(Value::as_i64)
+                            .ok_or(Error::InvalidAsm)?;
 
                         Some((file, line))
                     }
                     _ => None,
                 };
+                let address = node["address"].as_u64();
+                let opcodes = match &node["opcodes"] {
+                    Value::Array(opcodes) => opcodes
+                        .iter()
+                        .filter_map(|opcode| opcode.as_str().map(|opcode| opcode.to_owned()))
+                        .collect(),
+                    _ => vec![],
+                };
 
-                result.push(AsmOutput { text, source })
+                result.push(AsmOutput {
+                    text,
+                    source,
+                    address,
+                    opcodes,
+                })
             }
             Ok(result)
         }
@@ -71,6 +414,143 @@ fn parse_asm(json_array: &Value) -> Result<Vec<AsmOutput>, Error> {
     }
 }
 
+/// Parses outputs that come either as plain text, as a list of lines or, for newer instances, as an
+/// object with the list of lines in `asm`.
+fn parse_lines(json: &Value) -> Option<Vec<AsmOutput>> {
+    match json {
+        Value::String(text) => Some(
+            text.lines()
+                .map(|line| AsmOutput {
+                    text: line.to_owned(),
+                    ..Default::default()
+                })
+                .collect(),
+        ),
+        Value::Array(_) => parse_asm(json).ok(),
+        Value::Object(map) => map.get("asm").and_then(parse_lines),
+        _ => None,
+    }
+}
+
+fn parse_gcc_dump_pass(json: &Value) -> Option<GccDumpPass> {
+    Some(GccDumpPass {
+        name: json["name"].as_str()?.to_owned(),
+        command_prefix: json["command_prefix"]
+            .as_str()
+            .unwrap_or_default()
+            .to_owned(),
+        filename_suffix: json["filename_suffix"]
+            .as_str()
+            .unwrap_or_default()
+            .to_owned(),
+    })
+}
+
+fn parse_gcc_dump(json: &Value) -> Option<GccDump> {
+    let passes = match &json["all"] {
+        Value::Array(passes) => passes.iter().filter_map(parse_gcc_dump_pass).collect(),
+        _ => vec![],
+    };
+    Some(GccDump {
+        passes,
+        selected_pass: parse_gcc_dump_pass(&json["selectedPass"]),
+        output: json["currentPassOutput"].as_str()?.to_owned(),
+    })
+}
+
+fn parse_opt_pipeline(json: &Value) -> Option<Vec<OptPipelineFunction>> {
+    let text_lines = |json: &Value| match json {
+        Value::Array(lines) => lines
+            .iter()
+            .filter_map(|line| line["text"].as_str().map(|text| text.to_owned()))
+            .collect(),
+        _ => vec![],
+    };
+
+    // Newer instances wrap the functions in `results`
+    let functions = json.get("results").unwrap_or(json).as_object()?;
+    let functions = functions
+        .iter()
+        .map(|(name, passes)| OptPipelineFunction {
+            name: name.clone(),
+            passes: match passes {
+                Value::Array(passes) => passes
+                    .iter()
+                    .map(|pass| OptPipelinePass {
+                        name: pass["name"].as_str().unwrap_or_default().to_owned(),
+                        machine: pass["machine"].as_bool().unwrap_or(false),
+                        changed: pass["irChanged"].as_bool().unwrap_or(false),
+                        before: text_lines(&pass["before"]),
+                        after: text_lines(&pass["after"]),
+                    })
+                    .collect(),
+                _ => vec![],
+            },
+        })
+        .collect();
+    Some(functions)
+}
+
+/// Parses the remarks in the LLVM optimization record format, which compiler explorer also uses
+/// for gcc.
+fn parse_opt_remarks(json: &Value) -> Option<Vec<OptRemark>> {
+    let remarks = json.as_array()?;
+    let remarks = remarks
+        .iter()
+        .filter_map(|remark| {
+            let kind = match remark["optType"].as_str()? {
+                "Passed" => RemarkKind::Passed,
+                "Missed" => RemarkKind::Missed,
+                "Analysis" => RemarkKind::Analysis,
+                _ => return None,
+            };
+            let location = &remark["DebugLoc"];
+            let location = location["Line"]
+                .as_i64()
+                .map(|line| (line, location["Column"].as_i64().unwrap_or(0)));
+            // Older instances don't send `displayString`, the message is split across `Args`
+            let message = match remark["displayString"].as_str() {
+                Some(message) => message.to_owned(),
+                None => match &remark["Args"] {
+                    Value::Array(args) => args
+                        .iter()
+                        .filter_map(|arg| arg.as_object()?.values().find_map(Value::as_str))
+                        .collect(),
+                    _ => String::new(),
+                },
+            };
+            Some(OptRemark {
+                kind,
+                pass: remark["Pass"].as_str().unwrap_or_default().to_owned(),
+                function: remark["Function"]
+                    .as_str()
+                    .map(|function| function.to_owned()),
+                location,
+                message,
+            })
+        })
+        .collect();
+    Some(remarks)
+}
+
+/// Older instances return the documentation directly instead of wrapping it in `result`.
+fn parse_instruction_docs(json: &Value) -> Option<InstructionDocs> {
+    if json["found"] == Value::Bool(false) {
+        return None;
+    }
+    let docs = json.get("result").unwrap_or(json);
+    let html = docs["html"].as_str().unwrap_or_default().to_owned();
+    let tooltip = docs["tooltip"].as_str().unwrap_or_default().to_owned();
+    if html.is_empty() && tooltip.is_empty() {
+        return None;
+    }
+    Some(InstructionDocs {
+        tooltip,
+        html,
+        url: docs["url"].as_str().map(|url| url.to_owned()),
+    })
+}
+
 fn parse_tag(json_map: &Value) -> Option<(i64, String)> {
     match json_map {
         Value::Object(map) => {
@@ -88,7 +568,8 @@ fn parse_tag(json_map: &Value) -> Option<(i64, String)> {
     }
 }
 
-fn parse_stream(json_array: &Value) -> Result<Vec<StreamOutput>, Error> {
+/// Parses the `stdout` or `stderr` array of a compilation or execution result.
+pub fn parse_stream(json_array: &Value) -> Result<Vec<StreamOutput>, Error> {
     match json_array {
         Value::Array(array) => {
             let mut result = vec![];
@@ -108,118 +589,301 @@ fn parse_stream(json_array: &Value) -> Result<Vec<StreamOutput>, Error> {
     }
 }
 
-pub async fn execute(
-    ce_instance: &str,
-    compiler: &str,
-    src: &str,
-    arguments: &[String],
-) -> Result<ExecutionResult, Error> {
-    let args = arguments.join(" ");
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_RETRIES: u32 = 3;
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// Client for a Compiler Explorer instance.
+///
+/// Cloning is cheap and all clones share the same connection pool, so a single client should be
+/// created per instance and handed to every task talking to it.
+#[derive(Clone, Debug)]
+pub struct CompilerExplorerClient {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl CompilerExplorerClient {
+    pub fn new(base_url: &str, timeout: Duration) -> Result<Self, Error> {
+        let client = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .timeout(timeout)
+            .connect_timeout(CONNECT_TIMEOUT)
+            .build()?;
+
+        Ok(Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_owned(),
+        })
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub async fn execute(
+        &self,
+        compiler: &str,
+        src: &str,
+        options: &CompileOptions,
+    ) -> Result<ExecutionResult, Error> {
+        let args = options.arguments.join(" ");
+
+        let request_body = serde_json::json!({
+            "source": src,
+            "options": {
+                "userArguments": args,
+                "compilerOptions": {
+                    "skipAsm": true,
+                    "executorRequest": true
+                },
+                "libraries": options.libraries,
+            },
+            "files": options.files,
+            "allowStoreCodeDebug": true
+        });
+
+        let json = self
+            .post(
+                &format!("/api/compiler/{}/compile", compiler),
+                &request_body,
+            )
+            .await?;
+
+        let result = ExecutionResult {
+            code: json
+                .get("code")
+                .and_then(Value::as_i64)
+                .ok_or(Error::InvalidErrorCode)?,
+            stdout: json
+                .get("stdout")
+                .and_then(|data| parse_stream(data).ok())
+                .unwrap_or_default(),
+            stderr: json
+                .get("stderr")
+                .and_then(|data| parse_stream(data).ok())
+                .unwrap_or_default(),
+        };
+        Ok(result)
+    }
+
+    pub async fn compile(
+        &self,
+        compiler: &str,
+        src: &str,
+        options: &CompileOptions,
+    ) -> Result<CompilationResult, Error> {
+        let args = options.arguments.join(" ");
+
+        let mut request_body = serde_json::json!({
+            "source": src,
+            "options": {
+                "userArguments": args,
+                "compilerOptions": options.produce.compiler_options(),
+                "libraries": options.libraries,
+            },
+            "files": options.files,
+            "allowStoreCodeDebug": true
+        });
+        // The instance applies the same defaults when no filters are given
+        if options.filters != Filters::default() {
+            request_body["options"]["filters"] = serde_json::json!(options.filters);
+        }
+
+        let json = self
+            .post(
+                &format!("/api/compiler/{}/compile", compiler),
+                &request_body,
+            )
+            .await?;
+
+        let execution_result = if options.execute {
+            Some(self.execute(compiler, src, options).await?)
+        } else {
+            None
+        };
+
+        let result = CompilationResult {
+            code: json
+                .get("code")
+                .and_then(Value::as_i64)
+                .ok_or(Error::InvalidErrorCode)?,
+            stdout: json
+                .get("stdout")
+                .and_then(|data| parse_stream(data).ok())
+                .unwrap_or_default(),
+            stderr: json
+                .get("stderr")
+                .and_then(|data| parse_stream(data).ok())
+                .unwrap_or_default(),
+            asm: json
+                .get("asm")
+                .and_then(|data| parse_asm(data).ok())
+                .unwrap_or_default(),
+            execution: execution_result,
+            ir: json.get("irOutput").and_then(parse_lines),
+            ast: json.get("astOutput").and_then(parse_lines),
+            gcc_dump: json.get("gccDumpOutput").and_then(parse_gcc_dump),
+            opt_pipeline: json.get("optPipelineOutput").and_then(parse_opt_pipeline),
+            opt_remarks: json.get("optOutput").and_then(parse_opt_remarks),
+        };
+
+        Ok(result)
+    }
+
+    pub async fn compilers(&self) -> Result<Vec<Compiler>, Error> {
+        let json = self
+            .get("/api/compilers?fields=id,name,lang,instructionSet")
+            .await?;
+        serde_json::from_value(json).map_err(|_| Error::InvalidCompilerList)
+    }
+
+    pub async fn languages(&self) -> Result<Vec<Language>, Error> {
+        let json = self.get("/api/languages?fields=id,name,extensions").await?;
+        serde_json::from_value(json).map_err(|_| Error::InvalidLanguageList)
+    }
+
+    /// Instruction set of `compiler`, as used by the instruction documentation endpoint.
+    pub async fn instruction_set(&self, compiler: &str) -> Result<Option<String>, Error> {
+        Ok(self
+            .compilers()
+            .await?
+            .into_iter()
+            .find(|entry| entry.id == compiler)
+            .and_then(|entry| entry.instruction_set))
+    }
+
+    /// Stores `src` compiled by `compiler` as a session of the instance, returning a short link
+    /// that opens it in the web UI.
+    pub async fn shorten(
+        &self,
+        language: &str,
+        compiler: &str,
+        src: &str,
+        options: &CompileOptions,
+    ) -> Result<String, Error> {
+        let request_body = serde_json::json!({
+            "sessions": [{
+                "id": 1,
+                "language": language,
+                "source": src,
+                "compilers": [{
+                    "id": compiler,
+                    "options": options.arguments.join(" "),
+                    "filters": options.filters,
+                    "libs": options.libraries,
+                }],
+            }],
+        });
+
+        let json = self.post("/api/shortener", &request_body).await?;
+        json.get("url")
+            .and_then(Value::as_str)
+            .map(|url| url.to_owned())
+            .ok_or(Error::InvalidShortLink)
+    }
 
-    let request_body = serde_json::json!({
-        "source": src,
-        "options": {
-            "userArguments": args,
-            "compilerOptions": {
-                "skipAsm": true,
-                "executorRequest": true
+    /// Documentation of `opcode` in `instruction_set` (`amd64`, `aarch64`, ...).
+    pub async fn instruction_docs(
+        &self,
+        instruction_set: &str,
+        opcode: &str,
+    ) -> Result<InstructionDocs, Error> {
+        let json = self
+            .get(&format!(
+                "/api/asm/{}/{}",
+                instruction_set,
+                opcode.to_lowercase()
+            ))
+            .await?;
+        parse_instruction_docs(&json).ok_or_else(|| Error::UnknownOpcode(opcode.to_owned()))
+    }
+
+    /// Posts `body` to `path` and returns the JSON object in the response.
+    async fn post(
+        &self,
+        path: &str,
+        body: &Value,
+    ) -> Result<serde_json::Map<String, Value>, Error> {
+        let request_url = format!("{}{}", self.base_url, path);
+        let response = self
+            .send(|| {
+                ::log::debug!("Post: {}", request_url);
+                self.client
+                    .post(&request_url)
+                    .header("Content-Type", "application/json")
+                    .header("Accept", "application/json")
+                    .json(body)
+            })
+            .await?;
+
+        let json: serde_json::Map<_, _> = response.json().await?;
+        log::info!("HTTP response: {:?}", json);
+        Ok(json)
+    }
+
+    async fn get(&self, path: &str) -> Result<Value, Error> {
+        let request_url = format!("{}{}", self.base_url, path);
+        let response = self
+            .send(|| {
+                ::log::debug!("Get: {}", request_url);
+                self.client
+                    .get(&request_url)
+                    .header("Accept", "application/json")
+            })
+            .await?;
+
+        let json: Value = response.json().await?;
+        log::debug!("HTTP response: {:?}", json);
+        Ok(json)
+    }
+
+    /// Sends the request built by `request`, retrying with exponential backoff when the instance
+    /// is overloaded (429) or failing (5xx).
+    async fn send(
+        &self,
+        request: impl Fn() -> reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, Error> {
+        let mut attempt = 0;
+        let response = loop {
+            let response = request().send().await?;
+
+            let status = response.status();
+            let retryable =
+                status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error();
+            if !retryable || attempt == MAX_RETRIES {
+                break response;
             }
-        },
-        "allowStoreCodeDebug": true
-    });
-
-    let client = reqwest::Client::new();
-    let request_url = format!("{}/api/compiler/{}/compile", ce_instance, compiler);
-    ::log::debug!("Post: {}", request_url);
-
-    let response = client
-        .post(request_url)
-        .header("Content-Type", "application/json")
-        .header("Accept", "application/json")
-        .json(&request_body)
-        .send()
-        .await?;
-
-    if response.status() != reqwest::StatusCode::OK {
-        log::error!("HTTP status code {}", response.status());
-        return Err(Error::InvalidHttpResponse(response.text().await?));
-    }
-
-    let json: serde_json::Map<_, _> = response.json().await?;
-    log::info!("HTTP response: {:?}", json);
-    let result = ExecutionResult {
-        code: json["code"].as_i64().ok_or(Error::InvalidErrorCode)?,
-        stdout: json
-            .get("stdout")
-            .and_then(|data| parse_stream(data).ok())
-            .unwrap_or(vec![]),
-        stderr: json
-            .get("stderr")
-            .and_then(|data| parse_stream(data).ok())
-            .unwrap_or(vec![]),
-    };
-    Ok(result)
-}
-
-pub async fn compile(
-    ce_instance: &str,
-    compiler: &str,
-    src: &str,
-    arguments: &[String],
-    run_program: bool,
-) -> Result<CompilationResult, Error> {
-    let args = arguments.join(" ");
-
-    let request_body = serde_json::json!({
-        "source": src,
-        "options": {
-            "userArguments": args,
-        },
-        "allowStoreCodeDebug": true
-    });
-
-    let client = reqwest::Client::new();
-    let request_url = format!("{}/api/compiler/{}/compile", ce_instance, compiler);
-    ::log::debug!("Post: {}", request_url);
-
-    let response = client
-        .post(request_url)
-        .header("Content-Type", "application/json")
-        .header("Accept", "application/json")
-        .json(&request_body)
-        .send()
-        .await?;
-
-    if response.status() != reqwest::StatusCode::OK {
-        log::error!("HTTP status code {}", response.status());
-        return Err(Error::InvalidHttpResponse(response.text().await?));
-    }
-
-    let json: serde_json::Map<_, _> = response.json().await?;
-    log::info!("HTTP response: {:?}", json);
-
-    let execution_result = if run_program {
-        Some(execute(ce_instance, compiler, src, arguments).await?)
-    } else {
-        None
-    };
 
-    let result = CompilationResult {
-        code: json["code"].as_i64().ok_or(Error::InvalidErrorCode)?,
-        stdout: json
-            .get("stdout")
-            .and_then(|data| parse_stream(data).ok())
-            .unwrap_or(vec![]),
-        stderr: json
-            .get("stderr")
-            .and_then(|data| parse_stream(data).ok())
-            .unwrap_or(vec![]),
-        asm: json
-            .get("asm")
-            .and_then(|data| parse_asm(data).ok())
-            .unwrap_or(vec![]),
-        execution: execution_result,
-    };
+            let delay = retry_after(&response)
+                .unwrap_or(INITIAL_BACKOFF * 2u32.pow(attempt))
+                .min(MAX_RETRY_DELAY);
+            log::warn!(
+                "HTTP status code {}, retrying in {:?} ({}/{})",
+                status,
+                delay,
+                attempt + 1,
+                MAX_RETRIES
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        };
+
+        if response.status() != reqwest::StatusCode::OK {
+            log::error!("HTTP status code {}", response.status());
+            let status = response.status();
+            return Err(Error::InvalidHttpResponse(status, response.text().await?));
+        }
+        Ok(response)
+    }
+}
 
-    return Ok(result);
+/// Parses the `Retry-After` header. Only the delay-seconds form is supported, servers sending an
+/// HTTP date fall back to the regular backoff.
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    let value = response.headers().get(reqwest::header::RETRY_AFTER)?;
+    let seconds = value.to_str().ok()?.trim().parse().ok()?;
+    Some(Duration::from_secs(seconds))
 }
@@ -0,0 +1,119 @@
+//! User configuration, read from `ce/config.toml` in the configuration directory
+//! (`~/.config/ce/config.toml` on Linux).
+//!
+//! ```toml
+//! [keys]
+//! scroll_down = ["j", "down", "ctrl-n"]
+//! quit = ["q"]
+//!
+//! [theme]
+//! preset = "light"
+//! selection = "#c6dbf0"
+//! ```
+//!
+//! The `[layout]` section is described in the `layout` module.
+
+use crate::keymap::Action;
+use crate::layout::Layout;
+use crate::theme::{self, Preset, Theme};
+
+use serde::de::IntoDeserializer;
+use serde::{Deserialize as _, Deserializer};
+use serde_derive::Deserialize;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Unable to read {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    #[error("Invalid configuration in {0}: {1}")]
+    Parse(PathBuf, toml::de::Error),
+    #[error("Unable to write {0}: {1}")]
+    Write(PathBuf, std::io::Error),
+    #[error("Unable to serialize the configuration: {0}")]
+    Serialize(#[from] toml::ser::Error),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Key sequences of each action, replacing its default ones
+    #[serde(deserialize_with = "deserialize_keys")]
+    pub keys: HashMap<Action, Vec<String>>,
+    pub theme: ThemeConfig,
+    /// Panes and how they are arranged, the default one when unset
+    pub layout: Option<Layout>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct ThemeConfig {
+    pub preset: Preset,
+    /// Colors replacing those of the preset, by name
+    #[serde(flatten)]
+    pub colors: HashMap<String, String>,
+}
+
+impl ThemeConfig {
+    pub fn theme(&self) -> Result<Theme, theme::Error> {
+        Theme::new(self.preset, &self.colors)
+    }
+}
+
+impl Config {
+    pub fn default_path() -> Option<PathBuf> {
+        Some(dirs_next::config_dir()?.join("ce").join("config.toml"))
+    }
+
+    /// Loads the configuration at `path`, or at the default location when unset. Not having a
+    /// configuration at the default location is the same as having an empty one.
+    pub fn load(path: Option<&Path>) -> Result<Self, Error> {
+        let (path, required) = match path {
+            Some(path) => (path.to_owned(), true),
+            None => match Self::default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Self::default()),
+            },
+        };
+
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && !required => {
+                return Ok(Self::default())
+            }
+            Err(e) => return Err(Error::Io(path, e)),
+        };
+        toml::from_str(&contents).map_err(|e| Error::Parse(path, e))
+    }
+
+    /// Replaces the layout in the configuration at `path`, creating it if needed. The rest of
+    /// the configuration is kept, although not its comments or formatting.
+    pub fn save_layout(path: &Path, layout: &Layout) -> Result<(), Error> {
+        let mut table = match std::fs::read_to_string(path) {
+            Ok(contents) => toml::from_str(&contents).map_err(|e| Error::Parse(path.into(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => toml::value::Table::new(),
+            Err(e) => return Err(Error::Io(path.into(), e)),
+        };
+        table.insert("layout".to_owned(), toml::Value::try_from(layout)?);
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| Error::Write(path.into(), e))?;
+        }
+        std::fs::write(path, toml::to_string(&table)?).map_err(|e| Error::Write(path.into(), e))
+    }
+}
+
+/// TOML keys are always strings, which serde does not turn into enum variants on its own.
+fn deserialize_keys<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<HashMap<Action, Vec<String>>, D::Error> {
+    HashMap::<String, Vec<String>>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(action, sequences)| {
+            let action = Action::deserialize(action.as_str().into_deserializer())?;
+            Ok((action, sequences))
+        })
+        .collect()
+}
@@ -0,0 +1,174 @@
+//! Text buffer behind the editor pane, for snippets typed without a file.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    LineStart,
+    LineEnd,
+    /// Start of the next word, or of the next line at the end of this one
+    WordForward,
+    /// Start of this word, or of the previous one when already there
+    WordBackward,
+}
+
+/// Lines of text with a cursor. Columns count characters, and the cursor may sit right after
+/// the last one of its line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Buffer {
+    lines: Vec<String>,
+    row: usize,
+    column: usize,
+}
+
+impl Default for Buffer {
+    fn default() -> Self {
+        Self::new("")
+    }
+}
+
+impl Buffer {
+    pub fn new(text: &str) -> Self {
+        let mut buffer = Self {
+            lines: vec![],
+            row: 0,
+            column: 0,
+        };
+        buffer.set_text(text);
+        buffer
+    }
+
+    /// Replaces the text, keeping the cursor as close as it can to where it was.
+    pub fn set_text(&mut self, text: &str) {
+        self.lines = text.lines().map(|line| line.to_owned()).collect();
+        if self.lines.is_empty() {
+            self.lines.push(String::new());
+        }
+        self.set_cursor(self.row, self.column);
+    }
+
+    /// The lines, ending with a newline.
+    pub fn text(&self) -> String {
+        self.lines
+            .iter()
+            .map(|line| format!("{}\n", line))
+            .collect()
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// Row and column of the cursor.
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.row, self.column)
+    }
+
+    /// Moves the cursor to `row` and `column`, or to the closest position in the buffer.
+    pub fn set_cursor(&mut self, row: usize, column: usize) {
+        self.row = row.min(self.lines.len() - 1);
+        self.column = column.min(self.line_len());
+    }
+
+    pub fn move_cursor(&mut self, motion: Motion) {
+        let (row, column) = (self.row, self.column);
+        match motion {
+            Motion::Left => self.column = column.saturating_sub(1),
+            Motion::Right => self.column = (column + 1).min(self.line_len()),
+            Motion::Up => self.set_cursor(row.saturating_sub(1), column),
+            Motion::Down => self.set_cursor(row + 1, column),
+            Motion::LineStart => self.column = 0,
+            Motion::LineEnd => self.column = self.line_len(),
+            Motion::WordForward => {
+                let chars: Vec<char> = self.lines[row].chars().collect();
+                let mut next = column;
+                while next < chars.len() && is_word_char(chars[next]) {
+                    next += 1;
+                }
+                while next < chars.len() && !is_word_char(chars[next]) {
+                    next += 1;
+                }
+                if next == chars.len() && row + 1 < self.lines.len() {
+                    self.set_cursor(row + 1, 0);
+                } else {
+                    self.column = next;
+                }
+            }
+            Motion::WordBackward => {
+                if column == 0 {
+                    if row > 0 {
+                        self.set_cursor(row - 1, usize::MAX);
+                    }
+                    return;
+                }
+                let chars: Vec<char> = self.lines[row].chars().collect();
+                let mut previous = column;
+                while previous > 0 && !is_word_char(chars[previous - 1]) {
+                    previous -= 1;
+                }
+                while previous > 0 && is_word_char(chars[previous - 1]) {
+                    previous -= 1;
+                }
+                self.column = previous;
+            }
+        }
+    }
+
+    /// Inserts `c` before the cursor. A newline splits the line.
+    pub fn insert(&mut self, c: char) {
+        let offset = self.offset();
+        if c == '\n' {
+            let rest = self.lines[self.row].split_off(offset);
+            self.lines.insert(self.row + 1, rest);
+            self.row += 1;
+            self.column = 0;
+        } else {
+            self.lines[self.row].insert(offset, c);
+            self.column += 1;
+        }
+    }
+
+    /// Deletes the character before the cursor, joining the line with the previous one at its
+    /// start.
+    pub fn backspace(&mut self) {
+        if self.column > 0 {
+            self.column -= 1;
+            let offset = self.offset();
+            self.lines[self.row].remove(offset);
+        } else if self.row > 0 {
+            let line = self.lines.remove(self.row);
+            self.row -= 1;
+            self.column = self.line_len();
+            self.lines[self.row].push_str(&line);
+        }
+    }
+
+    /// Deletes the character under the cursor, joining the next line at the end of this one.
+    pub fn delete(&mut self) {
+        if self.column < self.line_len() {
+            let offset = self.offset();
+            self.lines[self.row].remove(offset);
+        } else if self.row + 1 < self.lines.len() {
+            let line = self.lines.remove(self.row + 1);
+            self.lines[self.row].push_str(&line);
+        }
+    }
+
+    fn line_len(&self) -> usize {
+        self.lines[self.row].chars().count()
+    }
+
+    /// Byte offset of the cursor in its line.
+    fn offset(&self) -> usize {
+        let line = &self.lines[self.row];
+        line.char_indices()
+            .nth(self.column)
+            .map_or(line.len(), |(offset, _)| offset)
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
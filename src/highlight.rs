@@ -0,0 +1,183 @@
+//! Syntax highlighting of assembly in the x86 (Intel and AT&T), ARM and RISC-V syntaxes.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Mnemonic,
+    Register,
+    /// Numbers and string literals
+    Immediate,
+    /// Label definitions and references to local labels or symbols
+    Label,
+    Comment,
+    Directive,
+    Text,
+}
+
+/// Splits an assembly line in tokens. Concatenating them gives back the line.
+pub fn tokenize(line: &str) -> Vec<(TokenKind, &str)> {
+    // Demangled labels can have spaces: `sum(int*, int):`
+    let label = line.trim_end();
+    if label.ends_with(':') && !line.starts_with(char::is_whitespace) {
+        return [
+            (TokenKind::Label, label),
+            (TokenKind::Text, &line[label.len()..]),
+        ]
+        .into_iter()
+        .filter(|(_, token)| !token.is_empty())
+        .collect();
+    }
+
+    let mut tokens = vec![];
+    let mut rest = line;
+    let mut first_word = true;
+
+    while !rest.is_empty() {
+        let c = rest.chars().next().unwrap();
+        let (kind, len) = if c.is_whitespace() {
+            (TokenKind::Text, take_while(rest, char::is_whitespace))
+        } else if is_comment(rest, tokens.last()) {
+            (TokenKind::Comment, rest.len())
+        } else if c == '"' {
+            (TokenKind::Immediate, string_len(rest))
+        } else if c == '<' && rest.contains('>') {
+            // Symbol of an address in disassembly: `call 1139 <square(int)>`
+            (TokenKind::Label, rest.find('>').unwrap() + 1)
+        } else if first_word {
+            first_word = false;
+            let len = take_while(rest, |c| !c.is_whitespace());
+            let word = &rest[..len];
+            if word.ends_with(':') {
+                // Labels are followed by the instruction, if any
+                first_word = true;
+                (TokenKind::Label, len)
+            } else if word.starts_with('.') {
+                (TokenKind::Directive, len)
+            } else {
+                (TokenKind::Mnemonic, len)
+            }
+        } else if let Some(len) = immediate_len(rest) {
+            (TokenKind::Immediate, len)
+        } else if is_identifier_start(c) {
+            let len = c.len_utf8() + take_while(&rest[c.len_utf8()..], is_identifier_char);
+            let word = &rest[..len];
+            let kind = if is_register(word.trim_start_matches('%')) {
+                TokenKind::Register
+            } else if word.starts_with(".L") {
+                TokenKind::Label
+            } else {
+                TokenKind::Text
+            };
+            (kind, len)
+        } else {
+            (TokenKind::Text, c.len_utf8())
+        };
+
+        tokens.push((kind, &rest[..len]));
+        rest = &rest[len..];
+    }
+    tokens
+}
+
+fn take_while(s: &str, predicate: impl Fn(char) -> bool) -> usize {
+    s.find(|c| !predicate(c)).unwrap_or(s.len())
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || matches!(c, '_' | '.' | '%' | '$')
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '$' | '@')
+}
+
+/// `#` starts comments in x86 and immediates in ARM, `@` starts comments in ARM and is part of
+/// symbols like `puts@PLT` elsewhere.
+fn is_comment(rest: &str, previous: Option<&(TokenKind, &str)>) -> bool {
+    let after_space = previous.is_none_or(|(kind, token)| {
+        *kind == TokenKind::Text && token.ends_with(char::is_whitespace)
+    });
+    rest.starts_with("//")
+        || rest.starts_with(';')
+        || (rest.starts_with('#') && immediate_len(rest).is_none())
+        || (rest.starts_with('@') && after_space)
+}
+
+fn string_len(rest: &str) -> usize {
+    let mut escaped = false;
+    for (index, c) in rest.char_indices().skip(1) {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => return index + 1,
+            _ => escaped = false,
+        }
+    }
+    rest.len()
+}
+
+/// Length of the number at the start of `rest`, with its `#` (ARM) or `$` (AT&T) prefix.
+fn immediate_len(rest: &str) -> Option<usize> {
+    let prefix = take_while(rest, |c| matches!(c, '#' | '$')).min(1);
+    let sign = rest[prefix..].starts_with('-') as usize;
+    let number = &rest[prefix + sign..];
+    if !number.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let len = take_while(number, |c| {
+        c.is_ascii_hexdigit() || matches!(c, 'x' | 'X' | '.')
+    });
+    Some(prefix + sign + len)
+}
+
+fn is_register(name: &str) -> bool {
+    const NAMED: &[&str] = &[
+        // x86
+        "rax", "rbx", "rcx", "rdx", "rsi", "rdi", "rbp", "rsp", "rip", "eax", "ebx", "ecx", "edx",
+        "esi", "edi", "ebp", "esp", "eip", "ax", "bx", "cx", "dx", "si", "di", "bp", "al", "bl",
+        "cl", "dl", "ah", "bh", "ch", "dh", "sil", "dil", "bpl", "spl", "cs", "ds", "es", "fs",
+        "gs", "ss", // ARM
+        "sp", "lr", "pc", "fp", "ip", "xzr", "wzr", "wsp", "nzcv", // RISC-V
+        "zero", "ra", "gp", "tp",
+    ];
+    // Prefix and the highest register number
+    const NUMBERED: &[(&str, u32)] = &[
+        ("r", 31),
+        ("xmm", 31),
+        ("ymm", 31),
+        ("zmm", 31),
+        ("k", 7),
+        ("x", 31),
+        ("w", 30),
+        ("v", 31),
+        ("q", 31),
+        ("d", 31),
+        ("s", 31),
+        ("h", 31),
+        ("b", 31),
+        ("t", 6),
+        ("a", 7),
+        ("f", 31),
+        ("ft", 11),
+        ("fs", 11),
+        ("fa", 7),
+    ];
+
+    let name = name.to_ascii_lowercase();
+    // Vector arrangements like `v0.4s` name the register too
+    let name = name.split('.').next().unwrap_or_default();
+    if NAMED.contains(&name) {
+        return true;
+    }
+    // x86 r8-r15 have sized variants: r8d, r8w, r8b
+    let name = match name.strip_prefix('r') {
+        Some(rest) if rest.starts_with(|c: char| c.is_ascii_digit()) => {
+            name.trim_end_matches(['d', 'w', 'b'])
+        }
+        _ => name,
+    };
+    NUMBERED.iter().any(|(prefix, max)| {
+        name.strip_prefix(prefix)
+            .filter(|number| !number.is_empty() && (number.len() == 1 || !number.starts_with('0')))
+            .and_then(|number| number.parse::<u32>().ok())
+            .is_some_and(|number| number <= *max)
+    })
+}
@@ -0,0 +1,462 @@
+//! Key bindings of the TUI.
+//!
+//! Every action can be bound to any number of key sequences, written as space separated keys
+//! with optional modifiers: `j`, `G`, `ctrl-d`, `g g`, `pagedown`.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde_derive::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Invalid key {0:?}")]
+    InvalidKey(String),
+    #[error("Invalid key sequence {sequence:?} for {action:?}: {error}")]
+    InvalidBinding {
+        action: Action,
+        sequence: String,
+        error: Box<Error>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    ScrollDown,
+    ScrollUp,
+    ScrollLeft,
+    ScrollRight,
+    Top,
+    Bottom,
+    HalfPageDown,
+    HalfPageUp,
+    PageDown,
+    PageUp,
+    NextPane,
+    PreviousPane,
+    PaneLeft,
+    PaneRight,
+    PaneUp,
+    PaneDown,
+    ToggleFocus,
+    GrowPane,
+    ShrinkPane,
+    /// Lays the panes out side by side instead of stacked, or the other way around
+    ToggleOrientation,
+    HidePane,
+    /// Shows the panes hidden with `HidePane` that have no action of their own
+    ShowPanes,
+    /// Writes the layout to the configuration file
+    SaveLayout,
+    NextTab,
+    PreviousTab,
+    /// Exits, or drops the visual selection if there is one
+    Quit,
+    Recompile,
+    ToggleAddresses,
+    ToggleOpcodes,
+    ToggleIr,
+    ToggleAst,
+    ToggleGccDump,
+    ToggleOptPipeline,
+    ToggleSource,
+    ToggleRemarks,
+    ToggleCfg,
+    ToggleOutline,
+    ToggleEditor,
+    /// Shows the editor pane and types into it until esc
+    Edit,
+    /// Opens the file of the tab in `$VISUAL` or `$EDITOR`, at the source line of the ASM cursor
+    OpenInEditor,
+    CycleRemarkFilter,
+    PreviousEntry,
+    NextEntry,
+    SearchForward,
+    SearchBackward,
+    NextMatch,
+    PreviousMatch,
+    Fold,
+    FoldAll,
+    VisualSelection,
+    Yank,
+    YankAll,
+    InstructionDocs,
+    Help,
+}
+
+impl Action {
+    /// Every action, in the order they are listed in the help
+    pub const ALL: [Action; 54] = [
+        Action::ScrollDown,
+        Action::ScrollUp,
+        Action::ScrollLeft,
+        Action::ScrollRight,
+        Action::Top,
+        Action::Bottom,
+        Action::HalfPageDown,
+        Action::HalfPageUp,
+        Action::PageDown,
+        Action::PageUp,
+        Action::NextPane,
+        Action::PreviousPane,
+        Action::PaneLeft,
+        Action::PaneRight,
+        Action::PaneUp,
+        Action::PaneDown,
+        Action::ToggleFocus,
+        Action::GrowPane,
+        Action::ShrinkPane,
+        Action::ToggleOrientation,
+        Action::HidePane,
+        Action::ShowPanes,
+        Action::SaveLayout,
+        Action::NextTab,
+        Action::PreviousTab,
+        Action::Quit,
+        Action::Recompile,
+        Action::ToggleAddresses,
+        Action::ToggleOpcodes,
+        Action::ToggleIr,
+        Action::ToggleAst,
+        Action::ToggleGccDump,
+        Action::ToggleOptPipeline,
+        Action::ToggleSource,
+        Action::ToggleRemarks,
+        Action::ToggleCfg,
+        Action::ToggleOutline,
+        Action::ToggleEditor,
+        Action::Edit,
+        Action::OpenInEditor,
+        Action::CycleRemarkFilter,
+        Action::PreviousEntry,
+        Action::NextEntry,
+        Action::SearchForward,
+        Action::SearchBackward,
+        Action::NextMatch,
+        Action::PreviousMatch,
+        Action::Fold,
+        Action::FoldAll,
+        Action::VisualSelection,
+        Action::Yank,
+        Action::YankAll,
+        Action::InstructionDocs,
+        Action::Help,
+    ];
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::ScrollDown => "Move the cursor down",
+            Action::ScrollUp => "Move the cursor up",
+            Action::ScrollLeft => "Scroll left",
+            Action::ScrollRight => "Scroll right",
+            Action::Top => "Go to the first line",
+            Action::Bottom => "Go to the last line",
+            Action::HalfPageDown => "Move half a page down",
+            Action::HalfPageUp => "Move half a page up",
+            Action::PageDown => "Move a page down",
+            Action::PageUp => "Move a page up",
+            Action::NextPane => "Select the next pane",
+            Action::PreviousPane => "Select the previous pane",
+            Action::PaneLeft => "Select the pane on the left",
+            Action::PaneRight => "Select the pane on the right",
+            Action::PaneUp => "Select the pane above",
+            Action::PaneDown => "Select the pane below",
+            Action::ToggleFocus => "Show only the selected pane, or all of them again",
+            Action::GrowPane => "Make the selected pane bigger",
+            Action::ShrinkPane => "Make the selected pane smaller",
+            Action::ToggleOrientation => "Switch between side by side and stacked panes",
+            Action::HidePane => "Hide the selected pane",
+            Action::ShowPanes => "Show the ASM, stdout and stderr panes again",
+            Action::SaveLayout => "Save the layout to the configuration file",
+            Action::NextTab => "Show the next file",
+            Action::PreviousTab => "Show the previous file",
+            Action::Quit => "Quit, or drop the visual selection",
+            Action::Recompile => "Compile again",
+            Action::ToggleAddresses => "Show or hide addresses",
+            Action::ToggleOpcodes => "Show or hide opcodes",
+            Action::ToggleIr => "Show or hide the LLVM IR pane",
+            Action::ToggleAst => "Show or hide the AST pane",
+            Action::ToggleGccDump => "Show or hide the GCC dump pane",
+            Action::ToggleOptPipeline => "Show or hide the optimization pipeline pane",
+            Action::ToggleSource => "Show or hide the source pane",
+            Action::ToggleRemarks => "Show or hide the optimization remarks pane",
+            Action::ToggleCfg => "Show or hide the control flow graph pane",
+            Action::ToggleOutline => "Show or hide the outline",
+            Action::ToggleEditor => "Show or hide the editor pane",
+            Action::Edit => "Type into the editor pane, esc to stop",
+            Action::OpenInEditor => "Open the file in $EDITOR at the line of the ASM cursor",
+            Action::CycleRemarkFilter => "Cycle the kind of optimization remarks shown",
+            Action::PreviousEntry => "Previous pass or function",
+            Action::NextEntry => "Next pass or function",
+            Action::SearchForward => "Search forward",
+            Action::SearchBackward => "Search backward",
+            Action::NextMatch => "Go to the next match",
+            Action::PreviousMatch => "Go to the previous match",
+            Action::Fold => "Fold or unfold a function",
+            Action::FoldAll => "Fold or unfold every function",
+            Action::VisualSelection => "Start or stop selecting lines",
+            Action::Yank => "Copy the selection or the cursor line",
+            Action::YankAll => "Copy the whole pane",
+            Action::InstructionDocs => "Show the documentation of the instruction",
+            Action::Help => "Show this help",
+        }
+    }
+}
+
+/// Bindings used for the actions the configuration does not mention.
+const DEFAULT_BINDINGS: &[(Action, &[&str])] = &[
+    (Action::ScrollDown, &["j", "down"]),
+    (Action::ScrollUp, &["k", "up"]),
+    (Action::ScrollLeft, &["h", "left"]),
+    (Action::ScrollRight, &["l", "right"]),
+    (Action::Top, &["g g", "home"]),
+    (Action::Bottom, &["G", "end"]),
+    (Action::HalfPageDown, &["ctrl-d"]),
+    (Action::HalfPageUp, &["ctrl-u"]),
+    (Action::PageDown, &["pagedown"]),
+    (Action::PageUp, &["pageup"]),
+    (Action::NextPane, &["tab"]),
+    (Action::PreviousPane, &["backtab"]),
+    (Action::PaneLeft, &["H"]),
+    (Action::PaneRight, &["L"]),
+    (Action::PaneUp, &["K"]),
+    (Action::PaneDown, &["J"]),
+    (Action::ToggleFocus, &["enter"]),
+    (Action::GrowPane, &["+", "="]),
+    (Action::ShrinkPane, &["-"]),
+    (Action::ToggleOrientation, &["O"]),
+    (Action::HidePane, &["x"]),
+    (Action::ShowPanes, &["X"]),
+    (Action::SaveLayout, &["W"]),
+    (Action::NextTab, &["g t"]),
+    (Action::PreviousTab, &["g T"]),
+    (Action::Quit, &["esc", "q"]),
+    (Action::Recompile, &["R"]),
+    (Action::ToggleAddresses, &["a"]),
+    (Action::ToggleOpcodes, &["b"]),
+    (Action::ToggleIr, &["i"]),
+    (Action::ToggleAst, &["t"]),
+    (Action::ToggleGccDump, &["d"]),
+    (Action::ToggleOptPipeline, &["p"]),
+    (Action::ToggleSource, &["s"]),
+    (Action::ToggleRemarks, &["r"]),
+    (Action::ToggleCfg, &["c"]),
+    (Action::ToggleOutline, &["o"]),
+    (Action::ToggleEditor, &["E"]),
+    (Action::Edit, &["e"]),
+    (Action::OpenInEditor, &["g e"]),
+    (Action::CycleRemarkFilter, &["f"]),
+    (Action::PreviousEntry, &["["]),
+    (Action::NextEntry, &["]"]),
+    (Action::SearchForward, &["/"]),
+    (Action::SearchBackward, &["?"]),
+    (Action::NextMatch, &["n"]),
+    (Action::PreviousMatch, &["N"]),
+    (Action::Fold, &["z"]),
+    (Action::FoldAll, &["Z"]),
+    (Action::VisualSelection, &["v"]),
+    (Action::Yank, &["y"]),
+    (Action::YankAll, &["Y"]),
+    (Action::InstructionDocs, &["m"]),
+    (Action::Help, &["f1"]),
+];
+
+/// A key press. Characters carry their case, so shift is only kept for other keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl Key {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let modifiers = match code {
+            KeyCode::Char(_) | KeyCode::BackTab => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        Self { code, modifiers }
+    }
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Self {
+        Self::new(event.code, event.modifiers)
+    }
+}
+
+const NAMED_KEYS: &[(&str, KeyCode)] = &[
+    ("esc", KeyCode::Esc),
+    ("enter", KeyCode::Enter),
+    ("tab", KeyCode::Tab),
+    ("backtab", KeyCode::BackTab),
+    ("backspace", KeyCode::Backspace),
+    ("space", KeyCode::Char(' ')),
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+    ("insert", KeyCode::Insert),
+    ("delete", KeyCode::Delete),
+];
+
+impl FromStr for Key {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidKey(s.to_owned());
+
+        let mut modifiers = KeyModifiers::NONE;
+        let mut name = s;
+        // A lone `-` is a key, not a separator
+        while let Some((modifier, rest)) = name.split_once('-').filter(|(_, rest)| !rest.is_empty())
+        {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(invalid()),
+            };
+            name = rest;
+        }
+
+        let mut chars = name.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+                KeyCode::Char(c.to_ascii_uppercase())
+            }
+            (Some(c), None) => KeyCode::Char(c),
+            _ => {
+                let lowercase = name.to_lowercase();
+                match NAMED_KEYS.iter().find(|(key, _)| *key == lowercase) {
+                    Some((_, code)) => *code,
+                    None => match lowercase.strip_prefix('f').map(str::parse::<u8>) {
+                        Some(Ok(n)) if (1..=12).contains(&n) => KeyCode::F(n),
+                        _ => return Err(invalid()),
+                    },
+                }
+            }
+        };
+        Ok(Key::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "ctrl-"),
+            (KeyModifiers::ALT, "alt-"),
+            (KeyModifiers::SHIFT, "shift-"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                f.write_str(name)?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "f{}", n),
+            code => match NAMED_KEYS.iter().find(|(_, named)| *named == code) {
+                Some((name, _)) => f.write_str(name),
+                None => write!(f, "{:?}", code),
+            },
+        }
+    }
+}
+
+fn parse_sequence(sequence: &str) -> Result<Vec<Key>, Error> {
+    let keys = sequence
+        .split_whitespace()
+        .map(str::parse)
+        .collect::<Result<Vec<Key>, _>>()?;
+    if keys.is_empty() {
+        return Err(Error::InvalidKey(sequence.to_owned()));
+    }
+    Ok(keys)
+}
+
+/// Result of looking up the keys pressed so far.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Lookup {
+    Action(Action),
+    /// The keys start a longer sequence
+    Pending,
+    None,
+}
+
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: HashMap<Vec<Key>, Action>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new(&HashMap::new()).expect("Invalid default key bindings")
+    }
+}
+
+impl Keymap {
+    /// Default bindings, with those of the actions in `overrides` replaced by the given
+    /// sequences. A sequence bound to several actions triggers the overriding one.
+    pub fn new(overrides: &HashMap<Action, Vec<String>>) -> Result<Self, Error> {
+        let mut bindings = HashMap::new();
+        for (action, sequences) in DEFAULT_BINDINGS {
+            if overrides.contains_key(action) {
+                continue;
+            }
+            for sequence in *sequences {
+                bindings.insert(parse_sequence(sequence)?, *action);
+            }
+        }
+        for (action, sequences) in overrides {
+            for sequence in sequences {
+                let keys = parse_sequence(sequence).map_err(|error| Error::InvalidBinding {
+                    action: *action,
+                    sequence: sequence.clone(),
+                    error: Box::new(error),
+                })?;
+                bindings.insert(keys, *action);
+            }
+        }
+        Ok(Self { bindings })
+    }
+
+    /// Action bound to `keys`, the keys pressed since the last action.
+    pub fn lookup(&self, keys: &[Key]) -> Lookup {
+        if let Some(action) = self.bindings.get(keys) {
+            return Lookup::Action(*action);
+        }
+        let is_prefix = self
+            .bindings
+            .keys()
+            .any(|sequence| sequence.len() > keys.len() && sequence.starts_with(keys));
+        if is_prefix {
+            Lookup::Pending
+        } else {
+            Lookup::None
+        }
+    }
+
+    /// Key sequences bound to `action`, formatted as in the configuration, shortest first.
+    pub fn sequences(&self, action: Action) -> Vec<String> {
+        let mut sequences: Vec<String> = self
+            .bindings
+            .iter()
+            .filter(|(_, bound)| **bound == action)
+            .map(|(keys, _)| {
+                keys.iter()
+                    .map(|key| key.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect();
+        sequences.sort_by(|a, b| a.len().cmp(&b.len()).then(a.cmp(b)));
+        sequences
+    }
+}
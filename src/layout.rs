@@ -0,0 +1,416 @@
+//! Arrangement of the panes on the screen.
+//!
+//! The layout is a tree of splits, each dividing its area between its children side by side or
+//! stacked, in proportion to their weight. Panes that are not shown give their room to the rest.
+//!
+//! ```toml
+//! [layout]
+//! shown = ["asm", "source", "stdout", "stderr"]
+//!
+//! [layout.root]
+//! direction = "horizontal"
+//! children = [
+//!     { pane = "source", weight = 8 },
+//!     { direction = "vertical", weight = 12, children = [
+//!         { pane = "asm", weight = 20 },
+//!         { pane = "stdout" },
+//!         { pane = "stderr" },
+//!     ] },
+//! ]
+//! ```
+
+use serde_derive::{Deserialize, Serialize};
+use tui::layout::{Constraint, Rect};
+
+use std::collections::HashSet;
+use std::ops::Range;
+
+/// Weight of the nodes that do not set one
+pub const DEFAULT_WEIGHT: u16 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Pane {
+    Asm,
+    Stdout,
+    Stderr,
+    Ir,
+    Ast,
+    GccDump,
+    OptPipeline,
+    Source,
+    /// Text typed in the UI, compiled in place of the file
+    Editor,
+    Remarks,
+    Cfg,
+    /// Always a sidebar on the left, whatever the layout says
+    Outline,
+}
+
+impl Pane {
+    /// Every pane, in the order of the default layout
+    pub const ALL: [Pane; 12] = [
+        Pane::Outline,
+        Pane::Source,
+        Pane::Editor,
+        Pane::Asm,
+        Pane::Cfg,
+        Pane::Ir,
+        Pane::OptPipeline,
+        Pane::GccDump,
+        Pane::Ast,
+        Pane::Remarks,
+        Pane::Stdout,
+        Pane::Stderr,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    /// Side by side
+    #[default]
+    Horizontal,
+    /// Stacked
+    Vertical,
+}
+
+impl Direction {
+    fn is_horizontal(&self) -> bool {
+        *self == Direction::Horizontal
+    }
+
+    pub fn toggled(self) -> Self {
+        match self {
+            Direction::Horizontal => Direction::Vertical,
+            Direction::Vertical => Direction::Horizontal,
+        }
+    }
+}
+
+impl From<Direction> for tui::layout::Direction {
+    fn from(direction: Direction) -> Self {
+        match direction {
+            Direction::Horizontal => tui::layout::Direction::Horizontal,
+            Direction::Vertical => tui::layout::Direction::Vertical,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl Side {
+    /// Direction of the splits with children on this side of each other
+    fn direction(self) -> Direction {
+        match self {
+            Side::Left | Side::Right => Direction::Horizontal,
+            Side::Up | Side::Down => Direction::Vertical,
+        }
+    }
+
+    /// The column or row of a point, whichever changes moving towards the side.
+    pub fn position(self, column: u16, row: u16) -> u16 {
+        match self.direction() {
+            Direction::Horizontal => column,
+            Direction::Vertical => row,
+        }
+    }
+
+    /// Start of `area` along the direction of the side, its first column or row.
+    pub fn start(self, area: Rect) -> u16 {
+        match self.direction() {
+            Direction::Horizontal => area.left(),
+            Direction::Vertical => area.top(),
+        }
+    }
+
+    /// End of `area` along the direction of the side, exclusive.
+    pub fn end(self, area: Rect) -> u16 {
+        match self.direction() {
+            Direction::Horizontal => area.right(),
+            Direction::Vertical => area.bottom(),
+        }
+    }
+}
+
+/// A pane, or a split of the area between the children.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Node {
+    /// Share of the area of the parent split, relative to the weights of the other children
+    pub weight: u16,
+    /// Pane shown in the area, which makes the node a leaf
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pane: Option<Pane>,
+    #[serde(skip_serializing_if = "Direction::is_horizontal")]
+    pub direction: Direction,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<Node>,
+}
+
+impl Default for Node {
+    fn default() -> Self {
+        Self::split(Direction::default(), vec![])
+    }
+}
+
+impl Node {
+    pub fn pane(pane: Pane) -> Self {
+        Self {
+            weight: DEFAULT_WEIGHT,
+            pane: Some(pane),
+            direction: Direction::default(),
+            children: vec![],
+        }
+    }
+
+    pub fn split(direction: Direction, children: Vec<Node>) -> Self {
+        Self {
+            weight: DEFAULT_WEIGHT,
+            pane: None,
+            direction,
+            children,
+        }
+    }
+
+    fn weight(&self) -> u32 {
+        self.weight.max(1) as u32
+    }
+
+    fn contains(&self, pane: Pane) -> bool {
+        self.pane == Some(pane) || self.children.iter().any(|child| child.contains(pane))
+    }
+
+    fn is_visible(&self, visible: &impl Fn(Pane) -> bool) -> bool {
+        match self.pane {
+            Some(pane) => visible(pane),
+            None => self.children.iter().any(|child| child.is_visible(visible)),
+        }
+    }
+
+    /// Panes of the tree, in order.
+    pub fn panes(&self) -> Vec<Pane> {
+        match self.pane {
+            Some(pane) => vec![pane],
+            None => self.children.iter().flat_map(Node::panes).collect(),
+        }
+    }
+
+    /// Indices of the visible children and the part of `area` each one gets.
+    fn visible_children(
+        &self,
+        area: Rect,
+        visible: &impl Fn(Pane) -> bool,
+    ) -> (Vec<usize>, Vec<Rect>) {
+        let children: Vec<usize> = (0..self.children.len())
+            .filter(|index| self.children[*index].is_visible(visible))
+            .collect();
+        if children.is_empty() {
+            return (children, vec![]);
+        }
+        let total = children
+            .iter()
+            .map(|index| self.children[*index].weight())
+            .sum();
+        let parts = tui::layout::Layout::default()
+            .direction(self.direction.into())
+            .constraints(
+                children
+                    .iter()
+                    .map(|index| Constraint::Ratio(self.children[*index].weight(), total))
+                    .collect::<Vec<_>>(),
+            )
+            .split(area);
+        (children, parts)
+    }
+
+    /// Areas of the panes for which `visible` holds, dividing `area` between them.
+    pub fn areas(&self, area: Rect, visible: &impl Fn(Pane) -> bool) -> Vec<(Pane, Rect)> {
+        if let Some(pane) = self.pane {
+            return if visible(pane) {
+                vec![(pane, area)]
+            } else {
+                vec![]
+            };
+        }
+        let (children, parts) = self.visible_children(area, visible);
+        children
+            .into_iter()
+            .zip(parts)
+            .flat_map(|(index, part)| self.children[index].areas(part, visible))
+            .collect()
+    }
+
+    /// Grows the node holding `pane` by `percent` of the weight of the innermost split where it
+    /// shares the area with other visible nodes, or shrinks it when negative. Returns whether
+    /// there is such a split.
+    pub fn resize(&mut self, pane: Pane, percent: i32, visible: &impl Fn(Pane) -> bool) -> bool {
+        let index = match self.children.iter().position(|child| child.contains(pane)) {
+            Some(index) => index,
+            None => return false,
+        };
+        if self.children[index].resize(pane, percent, visible) {
+            return true;
+        }
+        let visible_children: Vec<&Node> = self
+            .children
+            .iter()
+            .filter(|child| child.is_visible(visible))
+            .collect();
+        if visible_children.len() < 2 {
+            return false;
+        }
+        let total: u32 = visible_children.iter().map(|child| child.weight()).sum();
+        let step = match percent * total as i32 / 100 {
+            0 => percent.signum(),
+            step => step,
+        };
+        let child = &mut self.children[index];
+        child.weight = (child.weight as i32 + step).clamp(1, u16::MAX as i32) as u16;
+        true
+    }
+
+    /// Moves the edge of `pane` on `side` to `edge`, a column or row of the `area` the node is
+    /// laid out in, trading weight with the visible node on that side in the innermost split
+    /// where there is one. Returns whether there is such a split.
+    pub fn move_edge(
+        &mut self,
+        area: Rect,
+        pane: Pane,
+        side: Side,
+        edge: u16,
+        visible: &impl Fn(Pane) -> bool,
+    ) -> bool {
+        let (children, parts) = self.visible_children(area, visible);
+        let position = match children
+            .iter()
+            .position(|index| self.children[*index].contains(pane))
+        {
+            Some(position) => position,
+            None => return false,
+        };
+        if self.children[children[position]].move_edge(parts[position], pane, side, edge, visible) {
+            return true;
+        }
+        if self.direction != side.direction() {
+            return false;
+        }
+        let other = match side {
+            Side::Left | Side::Up => position.checked_sub(1),
+            Side::Right | Side::Down => Some(position + 1).filter(|other| *other < children.len()),
+        };
+        let other = match other {
+            Some(other) => other,
+            None => return false,
+        };
+
+        let (first, second) = (position.min(other), position.max(other));
+        let start = side.start(parts[first]);
+        let size = side.end(parts[second]) - start;
+        if size < 2 {
+            return true;
+        }
+        let first_size = edge.saturating_sub(start).clamp(1, size - 1) as u32;
+
+        // Scale the split up when needed so that the weights can tell every row or column apart
+        let (first, second) = (children[first], children[second]);
+        let pair = |children: &[Node]| children[first].weight() + children[second].weight();
+        if pair(&self.children) < size as u32 {
+            let factor = (size as u32).div_ceil(pair(&self.children));
+            for child in &mut self.children {
+                child.weight = (child.weight() * factor).min(u16::MAX as u32) as u16;
+            }
+        }
+        let pair = pair(&self.children);
+        let first_weight = (pair * first_size / size as u32).clamp(1, pair - 1);
+        self.children[first].weight = first_weight as u16;
+        self.children[second].weight = (pair - first_weight) as u16;
+        true
+    }
+
+    /// Drops the leaves of panes in `seen`, adding the rest to it.
+    fn dedup(&mut self, seen: &mut HashSet<Pane>) {
+        self.children.retain_mut(|child| match child.pane {
+            Some(pane) => seen.insert(pane),
+            None => {
+                child.dedup(seen);
+                true
+            }
+        });
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Layout {
+    /// Panes the user wants shown. The ASM, stdout and stderr panes are only shown when they
+    /// have content.
+    pub shown: Vec<Pane>,
+    pub root: Node,
+}
+
+impl Default for Layout {
+    /// Every pane side by side, in the order of `Pane::ALL`.
+    fn default() -> Self {
+        Self {
+            shown: vec![Pane::Asm, Pane::Stdout, Pane::Stderr],
+            root: Node::split(
+                Direction::Horizontal,
+                Pane::ALL
+                    .iter()
+                    .filter(|pane| **pane != Pane::Outline)
+                    .map(|pane| Node::pane(*pane))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl Layout {
+    /// Makes every pane but the outline appear in the tree exactly once, adding the missing ones
+    /// at the end of the root split.
+    pub fn normalize(&mut self) {
+        if self.root.pane.is_some() {
+            let leaf = std::mem::take(&mut self.root);
+            self.root.children.push(leaf);
+        }
+        let mut seen = HashSet::from([Pane::Outline]);
+        self.root.dedup(&mut seen);
+        for pane in Pane::ALL {
+            if !seen.contains(&pane) {
+                self.root.children.push(Node::pane(pane));
+            }
+        }
+        let mut shown = HashSet::new();
+        self.shown.retain(|pane| shown.insert(*pane));
+    }
+}
+
+fn overlaps(a: Range<u16>, b: Range<u16>) -> bool {
+    a.start < b.end && b.start < a.end
+}
+
+/// The closest of `areas` on `side` of `from` that is level with it.
+pub fn neighbor<T: Copy>(areas: &[(T, Rect)], from: Rect, side: Side) -> Option<T> {
+    areas
+        .iter()
+        .filter_map(|(item, area)| {
+            let rows = overlaps(from.top()..from.bottom(), area.top()..area.bottom());
+            let columns = overlaps(from.left()..from.right(), area.left()..area.right());
+            let (distance, level) = match side {
+                Side::Left => (from.left() as i32 - area.right() as i32, rows),
+                Side::Right => (area.left() as i32 - from.right() as i32, rows),
+                Side::Up => (from.top() as i32 - area.bottom() as i32, columns),
+                Side::Down => (area.top() as i32 - from.bottom() as i32, columns),
+            };
+            (distance >= 0 && level).then_some((distance, *item))
+        })
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, item)| item)
+}
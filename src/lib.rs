@@ -0,0 +1,13 @@
+pub mod backend;
+pub mod cache;
+pub mod cfg;
+pub mod compile_commands;
+pub mod compiler_explorer;
+pub mod config;
+pub mod editor;
+pub mod highlight;
+pub mod keymap;
+pub mod layout;
+pub mod local;
+pub mod outline;
+pub mod theme;
@@ -0,0 +1,878 @@
+use crate::backend::{Backend, BoxFuture};
+use crate::compiler_explorer::{
+    AsmOutput, CompilationResult, CompileOptions, Error, ExecutionResult, Filters, OptRemark,
+    RemarkKind, StreamOutput,
+};
+
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::{Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Base name of the files written to the scratch directory, the same one compiler explorer uses.
+const SOURCE_NAME: &str = "example";
+/// Diagnostics refer to the source as `<source>`, just like compiler explorer does.
+const SOURCE_PLACEHOLDER: &str = "<source>";
+const EXECUTION_TIMEOUT: Duration = Duration::from_secs(10);
+const OBJDUMP: &str = "objdump";
+const CXXFILT: &str = "c++filt";
+
+/// Data directives are kept even when directives are filtered out, they are the contents of
+/// constants and globals.
+const DATA_DIRECTIVES: &[&str] = &[
+    ".ascii", ".asciz", ".string", ".byte", ".short", ".hword", ".value", ".word", ".long", ".int",
+    ".quad", ".xword", ".octa", ".zero", ".float", ".single", ".double",
+];
+
+/// Compiles with a toolchain installed on this machine.
+///
+/// The compiler passed to `compile` is the executable to run, e.g. `gcc` or
+/// `/opt/llvm/bin/clang++`. It must understand the usual gcc command line (`-S`, `-g`, `-o`).
+pub struct LocalCompiler {
+    extension: String,
+    objdump: String,
+    cxxfilt: String,
+}
+
+impl LocalCompiler {
+    /// `extension` is given to the source file handed to the compiler, which is how gcc and clang
+    /// pick the language to compile.
+    pub fn new(extension: &str) -> Self {
+        Self {
+            extension: extension.to_owned(),
+            objdump: OBJDUMP.to_owned(),
+            cxxfilt: CXXFILT.to_owned(),
+        }
+    }
+
+    /// Disassembles binaries with `objdump` instead of the one in `PATH`, e.g. the one of a cross
+    /// toolchain.
+    pub fn with_objdump(mut self, objdump: &str) -> Self {
+        self.objdump = objdump.to_owned();
+        self
+    }
+
+    /// Demangles symbols with `cxxfilt` instead of the `c++filt` in `PATH`.
+    pub fn with_cxxfilt(mut self, cxxfilt: &str) -> Self {
+        self.cxxfilt = cxxfilt.to_owned();
+        self
+    }
+
+    async fn compile_local(
+        &self,
+        compiler: &str,
+        source: &str,
+        options: &CompileOptions,
+    ) -> Result<CompilationResult, Error> {
+        let dir = ScratchDir::new()?;
+        let source_path = dir
+            .path()
+            .join(format!("{}.{}", SOURCE_NAME, self.extension));
+        std::fs::write(&source_path, source)?;
+        let asm_path = dir.path().join(format!("{}.s", SOURCE_NAME));
+        // Intel syntax only exists for x86, which the compiler may not target even on an x86 host
+        let intel = options.filters.intel && is_x86(compiler, &options.arguments).await;
+
+        let (output, asm) = if options.filters.binary || options.filters.binary_object {
+            self.disassemble(compiler, &source_path, options, intel, &dir)
+                .await?
+        } else {
+            let mut command = Command::new(compiler);
+            command.arg("-S").arg("-g").arg("-o").arg(&asm_path);
+            if intel {
+                command.arg("-masm=intel");
+            }
+            command.args(&options.arguments).arg(&source_path);
+            let output = run(compiler, &mut command).await?;
+
+            let asm = if output.status.success() {
+                let asm = std::fs::read_to_string(&asm_path)?;
+                let mut asm = parse_asm(&asm, &options.filters, &source_path);
+                if options.filters.demangle {
+                    demangle(&self.cxxfilt, &mut asm).await;
+                }
+                asm
+            } else {
+                compilation_failed()
+            };
+            (output, asm)
+        };
+
+        let execution = if options.execute && output.status.success() {
+            Some(self.execute(compiler, &source_path, options, &dir).await?)
+        } else {
+            None
+        };
+
+        // LLVM IR and AST dumps are clang only, gcc would even take `-emit-llvm` as `-e mit-llvm`.
+        // Both print optimization remarks, but with different flags
+        let produce = &options.produce;
+        let clang = (produce.ir || produce.ast || produce.opt_info)
+            && output.status.success()
+            && is_clang(compiler).await;
+        let ir = if options.produce.ir && clang {
+            let args = ["-S", "-emit-llvm", "-o", "-"];
+            Some(self.produce(compiler, &source_path, options, &args).await?)
+        } else {
+            None
+        };
+        let ast = if options.produce.ast && clang {
+            let args = [
+                "-fsyntax-only",
+                "-Xclang",
+                "-ast-dump",
+                "-fno-color-diagnostics",
+            ];
+            Some(self.produce(compiler, &source_path, options, &args).await?)
+        } else {
+            None
+        };
+        let opt_remarks = if options.produce.opt_info && output.status.success() {
+            Some(
+                self.opt_remarks(compiler, &source_path, options, &dir, clang)
+                    .await?,
+            )
+        } else {
+            None
+        };
+
+        Ok(CompilationResult {
+            code: exit_code(&output),
+            stdout: parse_output(&output.stdout, &source_path),
+            stderr: parse_output(&output.stderr, &source_path),
+            asm,
+            execution,
+            ir,
+            ast,
+            // Dumps are written next to the output by gcc and the pipeline is only printed by
+            // clang with extra flags, neither is supported locally yet
+            gcc_dump: None,
+            opt_pipeline: None,
+            opt_remarks,
+        })
+    }
+
+    /// Compiles again asking the compiler to print its optimization remarks as diagnostics.
+    async fn opt_remarks(
+        &self,
+        compiler: &str,
+        source_path: &Path,
+        options: &CompileOptions,
+        dir: &ScratchDir,
+        clang: bool,
+    ) -> Result<Vec<OptRemark>, Error> {
+        let mut command = Command::new(compiler);
+        command
+            .arg("-S")
+            .arg("-g")
+            .arg("-o")
+            .arg(dir.path().join(format!("{}.remarks.s", SOURCE_NAME)));
+        if clang {
+            command
+                .arg("-Rpass=.*")
+                .arg("-Rpass-missed=.*")
+                .arg("-Rpass-analysis=.*")
+                .arg("-fno-color-diagnostics");
+        } else {
+            command.arg("-fopt-info-all");
+        }
+        command.args(&options.arguments).arg(source_path);
+        let output = run(compiler, &mut command).await?;
+        Ok(parse_remarks(
+            &String::from_utf8_lossy(&output.stderr),
+            source_path,
+        ))
+    }
+
+    /// Runs the compiler with `args` to produce one of the extra outputs (LLVM IR, AST...) on
+    /// stdout. If the compiler doesn't support it, its diagnostics are returned instead.
+    async fn produce(
+        &self,
+        compiler: &str,
+        source_path: &Path,
+        options: &CompileOptions,
+        args: &[&str],
+    ) -> Result<Vec<AsmOutput>, Error> {
+        let mut command = Command::new(compiler);
+        command.args(args).args(&options.arguments).arg(source_path);
+        let output = run(compiler, &mut command).await?;
+        let text = if output.status.success() {
+            &output.stdout
+        } else {
+            &output.stderr
+        };
+        Ok(String::from_utf8_lossy(text)
+            .lines()
+            .map(|line| AsmOutput {
+                text: expand_tabs(
+                    &line.replace(&*source_path.to_string_lossy(), SOURCE_PLACEHOLDER),
+                ),
+                ..Default::default()
+            })
+            .collect())
+    }
+
+    /// Builds an object file or a binary depending on the filters and disassembles it with
+    /// objdump.
+    async fn disassemble(
+        &self,
+        compiler: &str,
+        source_path: &Path,
+        options: &CompileOptions,
+        intel: bool,
+        dir: &ScratchDir,
+    ) -> Result<(Output, Vec<AsmOutput>), Error> {
+        let object_only = options.filters.binary_object && !options.filters.binary;
+        let output_path = dir.path().join(if object_only {
+            format!("{}.o", SOURCE_NAME)
+        } else {
+            format!("{}.out", SOURCE_NAME)
+        });
+
+        let mut command = Command::new(compiler);
+        if object_only {
+            command.arg("-c");
+        }
+        command
+            .arg("-g")
+            .arg("-o")
+            .arg(&output_path)
+            .args(&options.arguments)
+            .arg(source_path);
+        let output = run(compiler, &mut command).await?;
+        if !output.status.success() {
+            return Ok((output, compilation_failed()));
+        }
+
+        let mut command = Command::new(&self.objdump);
+        command.arg("-d").arg("-l");
+        if object_only {
+            // Show relocations, otherwise calls and data references all point to 0
+            command.arg("-r");
+        }
+        if options.filters.demangle {
+            command.arg("-C");
+        }
+        if intel {
+            command.arg("-M").arg("intel");
+        }
+        command.arg(&output_path);
+        let objdump = run(&self.objdump, &mut command).await?;
+        if !objdump.status.success() {
+            return Err(Error::Process(
+                self.objdump.clone(),
+                std::io::Error::other(String::from_utf8_lossy(&objdump.stderr).into_owned()),
+            ));
+        }
+
+        let asm = parse_objdump(
+            &String::from_utf8_lossy(&objdump.stdout),
+            &options.filters,
+            source_path,
+        );
+        Ok((output, asm))
+    }
+
+    async fn execute(
+        &self,
+        compiler: &str,
+        source_path: &Path,
+        options: &CompileOptions,
+        dir: &ScratchDir,
+    ) -> Result<ExecutionResult, Error> {
+        let binary_path = dir.path().join(SOURCE_NAME);
+
+        let mut command = Command::new(compiler);
+        command
+            .arg("-o")
+            .arg(&binary_path)
+            .args(&options.arguments)
+            .arg(source_path);
+        let build = run(compiler, &mut command).await?;
+        if !build.status.success() {
+            return Ok(ExecutionResult {
+                code: exit_code(&build),
+                stdout: parse_output(&build.stdout, source_path),
+                stderr: parse_output(&build.stderr, source_path),
+            });
+        }
+
+        let mut command = Command::new(&binary_path);
+        command.current_dir(dir.path());
+        let program = binary_path.to_string_lossy().into_owned();
+        match tokio::time::timeout(EXECUTION_TIMEOUT, run(&program, &mut command)).await {
+            Ok(output) => {
+                let output = output?;
+                Ok(ExecutionResult {
+                    code: exit_code(&output),
+                    stdout: parse_output(&output.stdout, source_path),
+                    stderr: parse_output(&output.stderr, source_path),
+                })
+            }
+            Err(_) => Ok(ExecutionResult {
+                code: -1,
+                stdout: vec![],
+                stderr: vec![StreamOutput {
+                    text: format!("Program timed out after {:?}", EXECUTION_TIMEOUT),
+                    tag: None,
+                }],
+            }),
+        }
+    }
+}
+
+impl Backend for LocalCompiler {
+    fn id(&self) -> String {
+        format!("local:{}", self.extension)
+    }
+
+    fn compile<'a>(
+        &'a self,
+        compiler: &'a str,
+        source: &'a str,
+        options: &'a CompileOptions,
+    ) -> BoxFuture<'a, Result<CompilationResult, Error>> {
+        Box::pin(self.compile_local(compiler, source, options))
+    }
+
+    fn instruction_set<'a>(&'a self, compiler: &'a str) -> BoxFuture<'a, Result<String, Error>> {
+        Box::pin(async move {
+            instruction_set(&target(compiler, &[]).await?)
+                .map(|isa| isa.to_owned())
+                .ok_or_else(|| Error::UnknownInstructionSet(compiler.to_owned()))
+        })
+    }
+}
+
+/// Maps the target triple printed by `-dumpmachine` to the instruction set names used by the
+/// compiler explorer documentation.
+pub fn instruction_set(triple: &str) -> Option<&'static str> {
+    let arch = triple.trim().split('-').next()?;
+    match arch {
+        "x86_64" | "i386" | "i486" | "i586" | "i686" => Some("amd64"),
+        "aarch64" | "arm64" => Some("aarch64"),
+        arch if arch.starts_with("arm") || arch.starts_with("thumb") => Some("arm32"),
+        "avr" => Some("avr"),
+        "powerpc" | "powerpc64" | "powerpc64le" => Some("power"),
+        _ => None,
+    }
+}
+
+/// Temporary directory holding the files of a single compilation, removed on drop.
+struct ScratchDir {
+    path: PathBuf,
+}
+
+impl ScratchDir {
+    /// Creates a new directory only the current user can access. Names are made hard to guess
+    /// and a directory that already exists is never reused, as anyone can write to `temp_dir`.
+    fn new() -> Result<Self, std::io::Error> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let mut builder = std::fs::DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        let mut attempts = 0;
+        loop {
+            let nanos = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.subsec_nanos());
+            let path = std::env::temp_dir().join(format!(
+                "ce-{}-{}-{:08x}",
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed),
+                nanos
+            ));
+            match builder.create(&path) {
+                Ok(()) => return Ok(Self { path }),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists && attempts < 10 => {
+                    attempts += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.path) {
+            ::log::warn!("Unable to remove {:?}: {}", self.path, e);
+        }
+    }
+}
+
+async fn run(program: &str, command: &mut Command) -> Result<Output, Error> {
+    ::log::debug!("Running: {:?}", command);
+    command
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| Error::Process(program.to_owned(), e))
+}
+
+async fn is_clang(compiler: &str) -> bool {
+    let mut command = Command::new(compiler);
+    command.arg("--version");
+    match run(compiler, &mut command).await {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).contains("clang"),
+        Err(_) => false,
+    }
+}
+
+/// The target triple `compiler` generates code for with `arguments`, which may change it with
+/// `-target` or `-m32`.
+async fn target(compiler: &str, arguments: &[String]) -> Result<String, Error> {
+    let mut command = Command::new(compiler);
+    command.args(arguments).arg("-dumpmachine");
+    let output = run(compiler, &mut command).await?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+async fn is_x86(compiler: &str, arguments: &[String]) -> bool {
+    match target(compiler, arguments).await {
+        Ok(triple) => instruction_set(&triple) == Some("amd64"),
+        Err(_) => false,
+    }
+}
+
+fn compilation_failed() -> Vec<AsmOutput> {
+    vec![AsmOutput {
+        text: "<Compilation failed>".to_owned(),
+        ..Default::default()
+    }]
+}
+
+fn exit_code(output: &Output) -> i64 {
+    output.status.code().map(i64::from).unwrap_or(-1)
+}
+
+/// Splits compiler or program output in lines, tagging diagnostics that point into the source.
+fn parse_output(output: &[u8], source_path: &Path) -> Vec<StreamOutput> {
+    let output = String::from_utf8_lossy(output);
+    let source_path = source_path.to_string_lossy();
+    output
+        .lines()
+        .map(|line| {
+            let text = line.replace(source_path.as_ref(), SOURCE_PLACEHOLDER);
+            let tag = parse_diagnostic(&text);
+            StreamOutput { text, tag }
+        })
+        .collect()
+}
+
+/// Parses the optimization remarks printed by gcc with `-fopt-info-all` and by clang with
+/// `-Rpass=.*`, `-Rpass-missed=.*` and `-Rpass-analysis=.*`. Remarks about other files are dropped.
+pub fn parse_remarks(output: &str, source_path: &Path) -> Vec<OptRemark> {
+    let source_path = source_path.to_string_lossy();
+    output
+        .lines()
+        .filter_map(|line| {
+            let rest = line.strip_prefix(source_path.as_ref())?.strip_prefix(':')?;
+            let mut parts = rest.splitn(4, ':');
+            let line = parts.next()?.parse().ok()?;
+            let column = parts.next()?.parse().ok()?;
+            let kind = parts.next()?.trim();
+            let message = parts.next()?.trim();
+
+            let (kind, pass, message) = match kind {
+                // gcc doesn't say which pass emitted the remark
+                "optimized" => (RemarkKind::Passed, "", message),
+                "missed" => (RemarkKind::Missed, "", message),
+                "note" => (RemarkKind::Analysis, "", message),
+                "remark" => {
+                    // clang appends the flag that enabled the remark, e.g. `[-Rpass=inline]`
+                    let (message, flag) = message.rsplit_once(" [-R")?;
+                    let (flag, pass) = flag.strip_suffix(']')?.split_once('=')?;
+                    let kind = match flag {
+                        "pass" => RemarkKind::Passed,
+                        "pass-missed" => RemarkKind::Missed,
+                        "pass-analysis" => RemarkKind::Analysis,
+                        _ => return None,
+                    };
+                    (kind, pass, message)
+                }
+                _ => return None,
+            };
+            Some(OptRemark {
+                kind,
+                pass: pass.to_owned(),
+                function: None,
+                location: Some((line, column)),
+                message: message.to_owned(),
+            })
+        })
+        .collect()
+}
+
+/// Parses `<source>:line:column: message` diagnostics.
+fn parse_diagnostic(text: &str) -> Option<(i64, String)> {
+    let rest = text.strip_prefix(SOURCE_PLACEHOLDER)?.strip_prefix(':')?;
+    let (line, rest) = rest.split_once(':')?;
+    let line = line.parse().ok()?;
+    let message = match rest.split_once(':') {
+        Some((column, message)) if column.parse::<u64>().is_ok() => message,
+        _ => rest,
+    };
+    Some((line, message.trim().to_owned()))
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum LineKind<'a> {
+    Label(&'a str),
+    Directive,
+    Data,
+    Comment,
+    Instruction,
+    Blank,
+}
+
+fn classify(line: &str) -> LineKind<'_> {
+    let trimmed = line.trim();
+    if trimmed.is_empty() {
+        return LineKind::Blank;
+    }
+    if ["#", ";", "@", "//"]
+        .iter()
+        .any(|prefix| trimmed.starts_with(prefix))
+    {
+        return LineKind::Comment;
+    }
+    if !line.starts_with(char::is_whitespace) {
+        if let Some((label, _)) = line.split_once(':') {
+            if !label.is_empty() && label.chars().all(is_symbol_char) {
+                return LineKind::Label(label);
+            }
+        }
+    }
+    if trimmed.starts_with('.') {
+        let name = trimmed.split_whitespace().next().unwrap_or_default();
+        if DATA_DIRECTIVES.contains(&name) {
+            return LineKind::Data;
+        }
+        return LineKind::Directive;
+    }
+    LineKind::Instruction
+}
+
+fn is_symbol_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '.' | '$' | '@')
+}
+
+fn symbols(line: &str) -> impl Iterator<Item = &str> {
+    line.split(|c: char| !is_symbol_char(c))
+        .filter(|symbol| !symbol.is_empty())
+}
+
+/// Returns the arguments of a directive, `.loc 1 2 3` yields `1 2 3`.
+fn directive_args<'a>(line: &'a str, directive: &str) -> Option<&'a str> {
+    let rest = line.trim().strip_prefix(directive)?;
+    if rest.is_empty() || rest.starts_with(char::is_whitespace) {
+        Some(rest.trim())
+    } else {
+        None
+    }
+}
+
+fn quoted_strings(args: &str) -> Vec<&str> {
+    args.split('"').skip(1).step_by(2).collect()
+}
+
+fn is_debug_section(section: &str) -> bool {
+    section.starts_with(".debug")
+        || section.starts_with(".note")
+        || section.starts_with("__DWARF")
+        || section == ".comment"
+}
+
+/// Tracks the section subsequent lines belong to.
+#[derive(Default)]
+struct Sections {
+    current: String,
+    previous: String,
+    stack: Vec<String>,
+}
+
+impl Sections {
+    /// Updates the current section, returns whether `line` was a section directive.
+    fn update(&mut self, line: &str) -> bool {
+        let section_name = |args: &str| {
+            args.split(|c: char| c == ',' || c.is_whitespace())
+                .next()
+                .unwrap_or_default()
+                .trim_matches('"')
+                .to_owned()
+        };
+
+        let new_section = if let Some(args) = directive_args(line, ".section") {
+            section_name(args)
+        } else if let Some(args) = directive_args(line, ".pushsection") {
+            self.stack.push(self.current.clone());
+            section_name(args)
+        } else if directive_args(line, ".popsection").is_some() {
+            self.stack.pop().unwrap_or_default()
+        } else if directive_args(line, ".previous").is_some() {
+            self.previous.clone()
+        } else if let Some(section) = [".text", ".data", ".bss"]
+            .iter()
+            .find(|section| directive_args(line, section).is_some())
+        {
+            section.to_string()
+        } else {
+            return false;
+        };
+
+        self.previous = std::mem::replace(&mut self.current, new_section);
+        true
+    }
+}
+
+/// Post-processes the output of `-S` the way compiler explorer filters do, and maps `.loc`
+/// directives to source lines.
+pub fn parse_asm(asm: &str, filters: &Filters, source_path: &Path) -> Vec<AsmOutput> {
+    let source_path = source_path.to_string_lossy();
+    let mut files: HashMap<&str, Option<String>> = HashMap::new();
+    let mut globals = HashSet::new();
+    let mut sections = Sections::default();
+    let mut location = None;
+    let mut lines = vec![];
+
+    for line in asm.lines() {
+        let kind = classify(line);
+
+        if kind == LineKind::Directive {
+            if sections.update(line) {
+                location = None;
+            } else if let Some(args) = directive_args(line, ".file") {
+                // Either `.file N "name"` or `.file N "dir" "name"`
+                let index = args.split_whitespace().next().unwrap_or_default();
+                let strings = quoted_strings(args);
+                let file = match strings[..] {
+                    [name] => Some(name.to_owned()),
+                    [dir, name] => Some(Path::new(dir).join(name).to_string_lossy().into_owned()),
+                    _ => None,
+                };
+                if let Some(file) = file {
+                    let file = if file == source_path {
+                        None
+                    } else {
+                        Some(file)
+                    };
+                    files.insert(index, file);
+                }
+            } else if let Some(args) = directive_args(line, ".loc") {
+                let mut args = args.split_whitespace();
+                let file = args.next().and_then(|index| files.get(index));
+                let line: Option<i64> = args.next().and_then(|line| line.parse().ok());
+                location = match (file, line) {
+                    (Some(file), Some(line)) if line > 0 => Some((file.clone(), line)),
+                    _ => None,
+                };
+            } else if let Some(args) = directive_args(line, ".globl")
+                .or_else(|| directive_args(line, ".global"))
+                .or_else(|| directive_args(line, ".type"))
+            {
+                if let Some(symbol) = symbols(args).next() {
+                    globals.insert(symbol);
+                }
+            }
+        }
+
+        if filters.directives && is_debug_section(&sections.current) {
+            continue;
+        }
+        lines.push((line, kind, location.clone()));
+    }
+
+    let used_labels: HashSet<&str> = lines
+        .iter()
+        .filter(|(_, kind, _)| matches!(kind, LineKind::Instruction | LineKind::Data))
+        .flat_map(|(line, _, _)| symbols(line))
+        .collect();
+
+    lines
+        .into_iter()
+        .filter(|(_, kind, _)| match kind {
+            LineKind::Blank => false,
+            LineKind::Comment => !filters.comment_only,
+            LineKind::Directive => !filters.directives,
+            LineKind::Label(label) => {
+                !filters.labels || used_labels.contains(label) || globals.contains(label)
+            }
+            LineKind::Data | LineKind::Instruction => true,
+        })
+        .map(|(line, kind, location)| AsmOutput {
+            text: expand_tabs(line),
+            source: match kind {
+                LineKind::Instruction | LineKind::Data => location,
+                _ => None,
+            },
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Parses the output of `objdump -d -l`, splitting instructions in address, opcodes and text.
+///
+/// With the `library_code` filter only functions with at least one instruction coming from the
+/// source are kept, which hides the C runtime when disassembling linked binaries.
+pub fn parse_objdump(objdump: &str, filters: &Filters, source_path: &Path) -> Vec<AsmOutput> {
+    let source_path = source_path.to_string_lossy();
+    // Functions and whether they contain code from the source
+    let mut functions: Vec<(Vec<AsmOutput>, bool)> = vec![];
+    let mut location = None;
+
+    for line in objdump.lines() {
+        if line.trim().is_empty()
+            || line.starts_with("Disassembly of section")
+            || line.contains("file format")
+        {
+            continue;
+        }
+
+        // Function headers look like `0000000000001139 <main>:`
+        if let Some((address, name)) = line.split_once(" <") {
+            if let (Ok(_), Some(name)) = (u64::from_str_radix(address, 16), name.strip_suffix(">:"))
+            {
+                location = None;
+                functions.push((
+                    vec![AsmOutput {
+                        text: format!("{}:", name),
+                        ..Default::default()
+                    }],
+                    false,
+                ));
+                continue;
+            }
+        }
+
+        let (function, from_source) = match functions.last_mut() {
+            Some(function) => function,
+            None => continue,
+        };
+
+        if !line.starts_with(char::is_whitespace) {
+            // Either `function():` or `/path/to/file.cpp:12 (discriminator 3)` from `-l`
+            let file_line = line.split(" (").next().unwrap_or_default();
+            if let Some((file, line)) = file_line.rsplit_once(':') {
+                if let Ok(line) = line.parse::<i64>() {
+                    let file = if file == source_path {
+                        None
+                    } else {
+                        Some(file.to_owned())
+                    };
+                    location = Some((file, line));
+                }
+            }
+            continue;
+        }
+
+        // Instructions look like `    1139:\t48 83 ec 08          \tsub    rsp,0x8`, long ones
+        // continue in the next line with only address and opcodes
+        let mut fields = line.split('\t');
+        let address = fields
+            .next()
+            .and_then(|address| address.trim().strip_suffix(':'))
+            .and_then(|address| u64::from_str_radix(address, 16).ok());
+        let address = match address {
+            Some(address) => address,
+            None => {
+                // Relocations and other annotations
+                function.push(AsmOutput {
+                    text: format!("        {}", line.trim().replace('\t', " ")),
+                    ..Default::default()
+                });
+                continue;
+            }
+        };
+        let opcodes: Vec<String> = fields
+            .next()
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(|opcode| opcode.to_owned())
+            .collect();
+        let text: Vec<&str> = fields.collect();
+
+        if text.is_empty() {
+            if let Some(previous) = function.last_mut() {
+                previous.opcodes.extend(opcodes);
+            }
+            continue;
+        }
+
+        if matches!(location, Some((None, _))) {
+            *from_source = true;
+        }
+        function.push(AsmOutput {
+            text: format!("        {}", expand_tabs(&text.join("\t"))),
+            source: location.clone(),
+            address: Some(address),
+            opcodes,
+        });
+    }
+
+    functions
+        .into_iter()
+        .filter(|(_, from_source)| *from_source || !filters.library_code)
+        .flat_map(|(function, _)| function)
+        .collect()
+}
+
+fn expand_tabs(line: &str) -> String {
+    const TAB_WIDTH: usize = 8;
+    let mut result = String::with_capacity(line.len());
+    for c in line.chars() {
+        if c == '\t' {
+            let spaces = TAB_WIDTH - result.chars().count() % TAB_WIDTH;
+            result.extend(std::iter::repeat_n(' ', spaces));
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// Demangles symbols with `cxxfilt`, leaving the assembly untouched if it is not available.
+async fn demangle(cxxfilt: &str, asm: &mut [AsmOutput]) {
+    let child = Command::new(cxxfilt)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            ::log::warn!("Unable to run {}: {}", cxxfilt, e);
+            return;
+        }
+    };
+
+    let input: String = asm.iter().map(|line| format!("{}\n", line.text)).collect();
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    // Feed stdin concurrently, c++filt may fill its stdout pipe before reading all the input
+    let writer = tokio::spawn(async move { stdin.write_all(input.as_bytes()).await });
+
+    let output = child.wait_with_output().await;
+    let _ = writer.await;
+    match output {
+        Ok(output) if output.status.success() => {
+            let demangled = String::from_utf8_lossy(&output.stdout);
+            let demangled: Vec<&str> = demangled.lines().collect();
+            if demangled.len() == asm.len() {
+                for (line, demangled) in asm.iter_mut().zip(demangled) {
+                    line.text = demangled.to_owned();
+                }
+            }
+        }
+        Ok(output) => ::log::warn!("{} failed with {}", cxxfilt, output.status),
+        Err(e) => ::log::warn!("Unable to run {}: {}", cxxfilt, e),
+    }
+}
@@ -1,25 +1,30 @@
-mod compiler_explorer;
+mod clipboard;
 mod log;
+mod terminal;
 mod tui;
 
-use crossterm::{
-    event::{Event, KeyCode, KeyEvent},
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+use ce::{
+    backend, cache, cfg, compile_commands, compiler_explorer, config, editor, highlight, keymap,
+    layout, local, outline, theme,
 };
-use tokio_stream::StreamExt;
 
-use ::tui::{backend::CrosstermBackend, Terminal};
+use crossterm::event::Event;
+use tokio_stream::StreamExt;
 
 use notify::{self, RecursiveMode, Watcher};
+use std::sync::Arc;
 use std::time::Duration;
 use structopt::StructOpt;
 
+/// Time without typing in the editor pane before compiling its text
+const EDIT_DEBOUNCE: Duration = Duration::from_millis(500);
+
 #[derive(StructOpt, Debug)]
 #[structopt(name = "ce", about = "Run compiler explorer on local sources")]
 struct Opts {
-    #[structopt(short, long, default_value = "clang_trunk")]
-    compiler: String,
+    /// Compiler id, or the compiler executable with --local [default: clang_trunk, or cc with --local]
+    #[structopt(short, long)]
+    compiler: Option<String>,
 
     #[structopt(short = "u", long = "url", default_value = "https://godbolt.org")]
     compiler_explorer_url: String,
@@ -27,19 +32,285 @@ struct Opts {
     #[structopt(short, long)]
     log: bool,
 
+    /// Stack the panes of the layout instead of laying them side by side
     #[structopt(short = "v", long = "vertical")]
     vertical_orientation: bool,
 
     #[structopt(short, long)]
     execute: bool,
 
-    #[structopt(name = "FILE")]
-    file: std::path::PathBuf,
+    /// Disassemble the linked binary, showing addresses and opcodes
+    #[structopt(short, long)]
+    binary: bool,
+
+    /// Disassemble the object file, showing addresses and opcodes
+    #[structopt(long)]
+    binary_object: bool,
+
+    /// Timeout in seconds for each request to the compiler explorer instance
+    #[structopt(short, long, default_value = "30")]
+    timeout: u64,
+
+    /// Library to link against, as id:version (e.g. fmt:trunk). Can be repeated
+    #[structopt(long = "lib", number_of_values = 1)]
+    libraries: Vec<compiler_explorer::Library>,
+
+    /// Always compile remotely, bypassing the local result cache
+    #[structopt(long)]
+    no_cache: bool,
+
+    /// Compile with a toolchain installed on this machine instead of a compiler explorer instance
+    #[structopt(long)]
+    local: bool,
+
+    /// objdump to disassemble binaries with, with --local [default: objdump]
+    #[structopt(long)]
+    objdump: Option<String>,
+
+    /// c++filt to demangle symbols with, with --local [default: c++filt]
+    #[structopt(long)]
+    cxxfilt: Option<String>,
+
+    /// Compilation database to take the flags of each file from, before those after `--`
+    #[structopt(long, parse(from_os_str))]
+    compile_commands: Option<std::path::PathBuf>,
 
-    #[structopt(name = "ARGS")]
+    /// Send the headers the source includes from its directory, or from the include directories
+    /// of the compilation database, along with it. Ignored with --local
+    #[structopt(long)]
+    bundle_headers: bool,
+
+    /// Configuration file [default: ce/config.toml in the user configuration directory]
+    #[structopt(long, parse(from_os_str))]
+    config: Option<std::path::PathBuf>,
+
+    /// Source files, each shown in its own tab. `-` reads the source from stdin
+    #[structopt(name = "FILE", required = true, parse(from_os_str))]
+    files: Vec<std::path::PathBuf>,
+
+    /// Arguments passed to the compiler, after `--`
+    #[structopt(name = "ARGS", last = true)]
     args: Vec<String>,
 }
 
+/// The tab of the compiled file, the generation of the compilation and its result.
+type CompilationEvent = (
+    usize,
+    u64,
+    Result<compiler_explorer::CompilationResult, compiler_explorer::Error>,
+);
+
+fn read_source(path: &std::path::Path) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    Ok(String::from_utf8(std::fs::read(path)?)?)
+}
+
+/// Everything needed to turn a source file into a `CompilationResult`.
+#[derive(Clone)]
+struct CompilationContext {
+    backend: Arc<dyn backend::Backend>,
+    cache: Option<cache::Cache>,
+    compiler: String,
+    options: compiler_explorer::CompileOptions,
+}
+
+impl CompilationContext {
+    async fn compile(
+        &self,
+        source: &str,
+    ) -> Result<compiler_explorer::CompilationResult, compiler_explorer::Error> {
+        // The program may read the clock or the environment, running it again can change its output
+        let cache = self.cache.as_ref().filter(|_| !self.options.execute);
+        let key = cache::Cache::key(&self.backend.id(), &self.compiler, source, &self.options);
+        if let Some(result) = cache.and_then(|cache| cache.get(&key)) {
+            return Ok(result);
+        }
+
+        let result = self
+            .backend
+            .compile(&self.compiler, source, &self.options)
+            .await?;
+        if let Some(cache) = cache {
+            cache.put(&key, &result);
+        }
+        Ok(result)
+    }
+}
+
+/// A file given on the command line, compiled and watched on its own.
+struct File {
+    path: std::path::PathBuf,
+    /// File events carry canonical paths. Unset for stdin, which is not watched
+    canonical: Option<std::path::PathBuf>,
+    context: CompilationContext,
+    compilation: Option<tokio::task::JoinHandle<()>>,
+    /// Results of older compilations are discarded
+    generation: u64,
+    /// Kept around to recompile when the UI needs other outputs from the compiler
+    source: Option<String>,
+    /// Set when bundling headers
+    bundling: Option<HeaderBundling>,
+    /// Why the file is not compiled with the flags of the compilation database
+    lookup_error: Option<String>,
+    /// Headers left out of the last compilation
+    skipped_headers: Vec<String>,
+}
+
+/// Where to find the headers to send along with a file.
+struct HeaderBundling {
+    /// Directories to bundle headers from besides the one of the file
+    include_dirs: Vec<std::path::PathBuf>,
+    /// Headers forced with `-include`, passed on for those that could be bundled
+    forced_includes: Vec<std::path::PathBuf>,
+    /// Arguments of the file, without the `-include` of bundled headers
+    arguments: Vec<String>,
+}
+
+impl File {
+    /// Problems with how the file is compiled, shown over each of its results.
+    fn warning(&self) -> Option<String> {
+        let mut warnings: Vec<String> = self.lookup_error.iter().cloned().collect();
+        if !self.skipped_headers.is_empty() {
+            warnings.push(format!(
+                "Headers not sent: {}",
+                self.skipped_headers.join(", ")
+            ));
+        }
+        (!warnings.is_empty()).then(|| warnings.join("\n"))
+    }
+
+    /// Compiles the last source read in the background, dropping whatever is still in flight.
+    fn compile(
+        &mut self,
+        tab: usize,
+        produce: compiler_explorer::ProduceOptions,
+        tx: &tokio::sync::mpsc::UnboundedSender<CompilationEvent>,
+    ) {
+        if let Some(compilation) = self.compilation.take() {
+            compilation.abort();
+        }
+        self.generation += 1;
+        self.context.options.produce = produce;
+        if let Some(source) = &self.source {
+            if let (Some(bundling), Some(dir)) = (
+                &self.bundling,
+                self.canonical.as_ref().and_then(|path| path.parent()),
+            ) {
+                let bundle = compile_commands::bundle_headers(
+                    source,
+                    dir,
+                    &bundling.include_dirs,
+                    &bundling.forced_includes,
+                );
+                let mut arguments = bundling.arguments.clone();
+                for name in bundle.forced_includes {
+                    arguments.push("-include".to_owned());
+                    arguments.push(name);
+                }
+                self.context.options.arguments = arguments;
+                self.context.options.files = bundle.files;
+                self.skipped_headers = bundle.skipped;
+            }
+            self.compilation = Some(spawn_compilation(
+                &self.context,
+                source.clone(),
+                tab,
+                self.generation,
+                tx,
+            ));
+        }
+    }
+}
+
+/// Runs `$VISUAL` or `$EDITOR` (`vi` when unset) on `path`, at `line` when given. Editors take
+/// the line as `+line` before the file, as vi, emacs and nano do.
+fn open_in_editor(path: &std::path::Path, line: Option<i64>) -> Result<(), String> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_owned());
+    // Editors are often set along with their arguments, like `code --wait`
+    let mut words = editor.split_whitespace();
+    let program = words.next().ok_or("$EDITOR is empty")?;
+    let mut command = std::process::Command::new(program);
+    command.args(words);
+    if let Some(line) = line {
+        command.arg(format!("+{}", line));
+    }
+    let status = command
+        .arg(path)
+        .status()
+        .map_err(|e| format!("Unable to run {}: {}", program, e))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("{} exited with {}", program, status))
+    }
+}
+
+/// Everything needed to look up the documentation of an instruction.
+#[derive(Clone)]
+struct DocsContext {
+    backend: Arc<dyn backend::Backend>,
+    /// The documentation always comes from a compiler explorer instance, even for local builds
+    client: compiler_explorer::CompilerExplorerClient,
+    cache: Option<cache::Cache>,
+    compiler: String,
+    /// Looked up on the first request
+    instruction_set: Arc<tokio::sync::OnceCell<String>>,
+}
+
+impl DocsContext {
+    async fn lookup(
+        &self,
+        opcode: &str,
+    ) -> Result<compiler_explorer::InstructionDocs, compiler_explorer::Error> {
+        let instruction_set = self
+            .instruction_set
+            .get_or_try_init(|| self.backend.instruction_set(&self.compiler))
+            .await?;
+        let key = cache::Cache::docs_key(self.client.base_url(), instruction_set, opcode);
+        if let Some(docs) = self.cache.as_ref().and_then(|cache| cache.get_value(&key)) {
+            return Ok(docs);
+        }
+
+        let docs = self
+            .client
+            .instruction_docs(instruction_set, opcode)
+            .await?;
+        if let Some(cache) = &self.cache {
+            cache.put_value(&key, &docs);
+        }
+        Ok(docs)
+    }
+}
+
+/// Compiles `source` in a background task, posting the result tagged with `tab` and
+/// `generation` to `tx`.
+fn spawn_compilation(
+    context: &CompilationContext,
+    source: String,
+    tab: usize,
+    generation: u64,
+    tx: &tokio::sync::mpsc::UnboundedSender<CompilationEvent>,
+) -> tokio::task::JoinHandle<()> {
+    let context = context.clone();
+    let tx = tx.clone();
+
+    tokio::spawn(async move {
+        ::log::debug!("Starting compilation {} of tab {}", generation, tab);
+        let result = context.compile(&source).await;
+        if let Ok(result) = &result {
+            ::log::info!(
+                "Compilation {} of tab {} finished with code {}",
+                generation,
+                tab,
+                result.code
+            );
+        }
+        // The receiver is gone only when we are shutting down
+        let _ = tx.send((tab, generation, result));
+    })
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let opts = Opts::from_args();
@@ -48,30 +319,149 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         log::configure_logger()?;
     }
 
-    let orientation = if opts.vertical_orientation {
-        println!("vertical orientation");
-        tui::Orientation::Vertical
+    let config = config::Config::load(opts.config.as_deref())?;
+    let keymap = keymap::Keymap::new(&config.keys)?;
+    let theme = config.theme.theme()?;
+
+    let mut layout = config.layout.clone().unwrap_or_default();
+    layout.normalize();
+    if opts.vertical_orientation {
+        layout.root.direction = layout::Direction::Vertical;
+    }
+    let config_path = opts.config.clone().or_else(config::Config::default_path);
+
+    let remote = Arc::new(compiler_explorer::CompilerExplorerClient::new(
+        &opts.compiler_explorer_url,
+        Duration::from_secs(opts.timeout),
+    )?);
+    let compiler = match &opts.compiler {
+        Some(compiler) => compiler.clone(),
+        None if opts.local => "cc".to_owned(),
+        None => "clang_trunk".to_owned(),
+    };
+    let cache = if opts.no_cache {
+        None
     } else {
-        println!("horizontal orientation");
-        tui::Orientation::Horizontal
+        cache::Cache::new()
     };
+    let context_for = |path: &std::path::Path, arguments: Vec<String>| {
+        let backend: Arc<dyn backend::Backend> = if opts.local {
+            let extension = path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .unwrap_or("cpp");
+            let mut local = local::LocalCompiler::new(extension);
+            if let Some(objdump) = &opts.objdump {
+                local = local.with_objdump(objdump);
+            }
+            if let Some(cxxfilt) = &opts.cxxfilt {
+                local = local.with_cxxfilt(cxxfilt);
+            }
+            Arc::new(local)
+        } else {
+            remote.clone()
+        };
+        CompilationContext {
+            backend,
+            // Local toolchains change under our feet and compile quickly, don't cache their results
+            cache: if opts.local { None } else { cache.clone() },
+            compiler: compiler.clone(),
+            options: compiler_explorer::CompileOptions {
+                arguments,
+                libraries: opts.libraries.clone(),
+                execute: opts.execute,
+                filters: compiler_explorer::Filters {
+                    binary: opts.binary,
+                    binary_object: opts.binary_object,
+                    ..Default::default()
+                },
+                produce: Default::default(),
+                files: vec![],
+            },
+        }
+    };
+    let bundle_headers = opts.bundle_headers && !opts.local;
 
-    let stdout = std::io::stdout();
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    let mut files = opts
+        .files
+        .iter()
+        .map(|path| {
+            // Stdin is read once, before the terminal takes it over
+            let (canonical, source) = if path.as_os_str() == "-" {
+                (None, Some(std::io::read_to_string(std::io::stdin())?))
+            } else {
+                (Some(std::fs::canonicalize(path)?), None)
+            };
 
-    enable_raw_mode()?;
-    execute!(terminal.backend_mut(), EnterAlternateScreen)?;
+            let mut flags = compile_commands::Flags::default();
+            let mut lookup_error = None;
+            if let (Some(database), Some(canonical)) = (&opts.compile_commands, &canonical) {
+                match compile_commands::lookup(database, canonical) {
+                    Ok(entry) => flags = entry.flags(opts.local),
+                    // Files the build does not know about, like a scratch file, still compile
+                    Err(e @ compile_commands::Error::NotFound(..)) => {
+                        lookup_error = Some(format!("{}, using the command line flags", e));
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            if bundle_headers {
+                // Bundled headers are written next to the source
+                flags.arguments.push("-I.".to_owned());
+            }
+            flags.arguments.extend(opts.args.iter().cloned());
 
-    let mut ui = tui::Ui::new(orientation);
-    ui.draw(&mut terminal)?;
+            let bundling = bundle_headers.then(|| HeaderBundling {
+                include_dirs: flags.include_dirs,
+                forced_includes: flags.forced_includes,
+                arguments: flags.arguments.clone(),
+            });
+            Ok(File {
+                path: path.clone(),
+                canonical,
+                context: context_for(path, flags.arguments),
+                compilation: None,
+                generation: 0,
+                source,
+                bundling,
+                lookup_error,
+                skipped_headers: vec![],
+            })
+        })
+        .collect::<Result<Vec<_>, Box<dyn std::error::Error + Send + Sync>>>()?;
+
+    let docs_context = DocsContext {
+        backend: files[0].context.backend.clone(),
+        client: (*remote).clone(),
+        cache: cache.clone(),
+        compiler: compiler.clone(),
+        instruction_set: Default::default(),
+    };
 
-    let cannonical_path = std::fs::canonicalize(&opts.file)?;
-    let parent = cannonical_path.parent().unwrap();
+    let mut terminal = terminal::TerminalSession::new()?;
 
+    let names = files
+        .iter()
+        .map(|file| match &file.canonical {
+            Some(_) => file.path.display().to_string(),
+            None => "stdin".to_owned(),
+        })
+        .collect();
+    let mut ui = tui::Ui::new(names, layout, keymap, theme);
+    ui.draw(&mut terminal)?;
+
+    // Editors often replace files instead of writing them, so watch their directories
     let (tx, rx) = std::sync::mpsc::channel();
     let mut watcher = notify::watcher(tx, Duration::from_millis(300))?;
-    watcher.watch(parent, RecursiveMode::NonRecursive)?;
+    let mut parents: Vec<_> = files
+        .iter()
+        .filter_map(|file| file.canonical.as_ref()?.parent())
+        .collect();
+    parents.sort();
+    parents.dedup();
+    for parent in parents {
+        watcher.watch(parent, RecursiveMode::NonRecursive)?;
+    }
 
     let (async_tx, mut notify_rx) = tokio::sync::mpsc::unbounded_channel();
 
@@ -85,32 +475,128 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         }
     });
 
-    let file_contents = String::from_utf8(std::fs::read(&opts.file)?)?;
-    let result = compiler_explorer::compile(
-        &opts.compiler_explorer_url,
-        &opts.compiler,
-        &file_contents,
-        &opts.args[..],
-        opts.execute,
-    )
-    .await?;
-    ui.set_data(result);
+    let (compilation_tx, mut compilation_rx) = tokio::sync::mpsc::unbounded_channel();
+
+    let (docs_tx, mut docs_rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut docs_lookup: Option<tokio::task::JoinHandle<()>> = None;
+
+    for (tab, file) in files.iter_mut().enumerate() {
+        let contents = match &file.source {
+            Some(source) => Ok(source.clone()),
+            None => read_source(&file.path),
+        };
+        match contents {
+            Ok(file_contents) => {
+                ui.set_source(tab, file_contents.clone());
+                file.source = Some(file_contents);
+                file.compile(tab, ui.produce(tab), &compilation_tx);
+                if let Some(warning) = file.warning() {
+                    ui.set_error(tab, warning);
+                }
+            }
+            Err(e) => {
+                ui.set_error(
+                    tab,
+                    format!("Unable to read {}: {}", file.path.display(), e),
+                );
+            }
+        }
+    }
     ui.draw(&mut terminal)?;
 
+    // Tabs edited in the UI, compiled once the typing stops
+    let mut edited = std::collections::HashSet::new();
+    let mut edit_deadline: Option<tokio::time::Instant> = None;
+
     let mut event_stream = crossterm::event::EventStream::new();
     loop {
         let event = event_stream.next();
         let notify_ev = notify_rx.recv();
+        let compilation_ev = compilation_rx.recv();
+        let docs_ev = docs_rx.recv();
+        let edit_ev = async {
+            match edit_deadline {
+                Some(deadline) => tokio::time::sleep_until(deadline).await,
+                None => std::future::pending().await,
+            }
+        };
 
         tokio::select! {
                 event = event => {
                     match event {
-                        Some(Ok(Event::Key(KeyEvent { code: KeyCode::Esc, .. }))) => {
-                            ::log::info!("Exiting");
-                            break;
-                        }
                         Some(Ok(Event::Key(event))) => {
-                            ui.handle_key_event(event, &mut terminal)?;
+                            match ui.handle_key_event(event, &mut terminal)? {
+                                Some(tui::Command::Recompile(tab)) => {
+                                    files[tab].compile(tab, ui.produce(tab), &compilation_tx);
+                                }
+                                Some(tui::Command::InstructionDocs(opcode)) => {
+                                    if let Some(lookup) = docs_lookup.take() {
+                                        lookup.abort();
+                                    }
+                                    let docs_context = docs_context.clone();
+                                    let docs_tx = docs_tx.clone();
+                                    docs_lookup = Some(tokio::spawn(async move {
+                                        let docs = docs_context.lookup(&opcode).await.map_err(|e| e.to_string());
+                                        let _ = docs_tx.send((opcode, docs));
+                                    }));
+                                }
+                                Some(tui::Command::Yank(text)) => {
+                                    let lines = text.lines().count();
+                                    let status = match clipboard::copy(&text) {
+                                        Ok(clipboard::Destination::Clipboard) => format!("Copied {} lines to the clipboard", lines),
+                                        Ok(clipboard::Destination::File(path)) => format!("Wrote {} lines to {}", lines, path.display()),
+                                        Err(e) => format!("Unable to copy: {}", e),
+                                    };
+                                    ui.set_status(status);
+                                    ui.draw(&mut terminal)?;
+                                }
+                                Some(tui::Command::SaveLayout(layout)) => {
+                                    let status = match &config_path {
+                                        Some(path) => match config::Config::save_layout(path, &layout) {
+                                            Ok(()) => format!("Saved the layout to {}", path.display()),
+                                            Err(e) => format!("Unable to save the layout: {}", e),
+                                        },
+                                        None => "Unable to save the layout: no configuration directory".to_owned(),
+                                    };
+                                    ui.set_status(status);
+                                    ui.draw(&mut terminal)?;
+                                }
+                                Some(tui::Command::Edit(tab, source)) => {
+                                    files[tab].source = Some(source);
+                                    edited.insert(tab);
+                                    edit_deadline = Some(tokio::time::Instant::now() + EDIT_DEBOUNCE);
+                                }
+                                Some(tui::Command::OpenInEditor(tab, line)) => {
+                                    // Stdin has no file to edit
+                                    let result = match files[tab].canonical {
+                                        Some(_) => {
+                                            // The stream keeps reading stdin in the background, which would take the
+                                            // keys meant for the editor and replay them once we are back
+                                            drop(event_stream);
+                                            // The editor owns the terminal until it exits, let compilations go on meanwhile
+                                            let result = tokio::task::block_in_place(|| {
+                                                terminal.suspend(|| open_in_editor(&files[tab].path, line))
+                                            });
+                                            event_stream = crossterm::event::EventStream::new();
+                                            result?
+                                        }
+                                        None => Err("stdin has no file to edit, use the editor pane".to_owned()),
+                                    };
+                                    // Saving the file triggers the watcher, which recompiles it
+                                    if let Err(e) = result {
+                                        ui.set_status(e);
+                                    }
+                                    ui.draw(&mut terminal)?;
+                                }
+                                Some(tui::Command::Quit) => {
+                                    ::log::info!("Exiting");
+                                    break;
+                                }
+                                None => {}
+                            }
+                        }
+                        Some(Ok(Event::Mouse(event))) => {
+                            ui.handle_mouse_event(event, &mut terminal)?;
                         }
                         Some(Ok(Event::Resize(_,_))) => {
                             ui.draw(&mut terminal)?;
@@ -121,22 +607,26 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
                 notify_ev = notify_ev => {
                     ::log::debug!("Received file event: {:?}", notify_ev);
                     match notify_ev {
-                    Some(notify::DebouncedEvent::Create(file)) | Some(notify::DebouncedEvent::Write(file))
-                        if std::fs::canonicalize(&file)? == cannonical_path => {
-                        let file_contents = std::fs::read(file)?;
-                        let file_contents = String::from_utf8(file_contents)?;
-
-                        let result = compiler_explorer::compile(
-                            &opts.compiler_explorer_url,
-                            &opts.compiler,
-                            &file_contents,
-                            &opts.args[..],
-                            opts.execute,
-                        )
-                        .await?;
-
-                        ui.set_data(result);
-                        ui.draw(&mut terminal)?;
+                    Some(notify::DebouncedEvent::Create(path)) | Some(notify::DebouncedEvent::Write(path)) => {
+                        let canonical = std::fs::canonicalize(&path).ok();
+                        // The same file may be given twice, under different paths
+                        for (tab, file) in files.iter_mut().enumerate() {
+                            if canonical.is_none() || canonical != file.canonical {
+                                continue;
+                            }
+                            match read_source(&path) {
+                                Ok(file_contents) => {
+                                    ui.set_source(tab, file_contents.clone());
+                                    file.source = Some(file_contents);
+                                    // Only the latest save matters, drop whatever is still in flight
+                                    file.compile(tab, ui.produce(tab), &compilation_tx);
+                                }
+                                Err(e) => {
+                                    ui.set_error(tab, format!("Unable to read {}: {}", path.display(), e));
+                                    ui.draw(&mut terminal)?;
+                                }
+                            }
+                        }
                     }
                     Some(notify::DebouncedEvent::Error(e, f)) => {
                         ::log::error!("Error {:?} watching file: {:?}", e, f);
@@ -145,14 +635,49 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
                     _ => {}
                 }
             }
+            Some((tab, result_generation, result)) = compilation_ev => {
+                if result_generation != files[tab].generation {
+                    ::log::debug!("Discarding stale compilation {} of tab {}", result_generation, tab);
+                    continue;
+                }
+                files[tab].compilation = None;
+                match result {
+                    Ok(result) => {
+                        ui.set_data(tab, result);
+                        if let Some(warning) = files[tab].warning() {
+                            ui.set_error(tab, warning);
+                        }
+                    }
+                    Err(e) => {
+                        ::log::error!("Compilation {} of tab {} failed: {}", result_generation, tab, e);
+                        // Keep the last good result around, the next save retries
+                        ui.set_error(tab, e.to_string());
+                    }
+                }
+                ui.draw(&mut terminal)?;
+            }
+            _ = edit_ev => {
+                edit_deadline = None;
+                for tab in edited.drain() {
+                    files[tab].compile(tab, ui.produce(tab), &compilation_tx);
+                }
+            }
+            Some((opcode, docs)) = docs_ev => {
+                ui.set_docs(&opcode, docs);
+                ui.draw(&mut terminal)?;
+            }
         }
     }
 
+    for compilation in files.iter_mut().filter_map(|file| file.compilation.take()) {
+        compilation.abort();
+    }
+    if let Some(lookup) = docs_lookup {
+        lookup.abort();
+    }
+
     // Notify async threads about shutdown
     shutdown_tx.send(())?;
 
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-    disable_raw_mode()?;
-
     Ok(())
 }
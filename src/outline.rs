@@ -0,0 +1,61 @@
+//! Function outline of the assembly returned by any backend.
+
+use crate::compiler_explorer::AsmOutput;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    /// Label of the function, demangled when possible
+    pub name: String,
+    /// Index of the label in the assembly
+    pub line: usize,
+    /// Index one past the last line of the function body
+    pub end: usize,
+    pub instructions: usize,
+}
+
+/// Finds the functions in `asm`. Any global label starts a function, which lasts until the next
+/// one. Local labels (`.L3:`) and directives are part of the function body but are not counted
+/// as instructions.
+pub fn parse(asm: &[AsmOutput]) -> Vec<Symbol> {
+    let mut symbols: Vec<Symbol> = vec![];
+    for (index, line) in asm.iter().enumerate() {
+        let text = line.text.trim();
+        if text.is_empty() {
+            continue;
+        }
+
+        if !line.text.starts_with(char::is_whitespace) && text.ends_with(':') {
+            let label = text.trim_end_matches(':');
+            if !label.starts_with('.') {
+                if let Some(last) = symbols.last_mut() {
+                    last.end = index;
+                }
+                symbols.push(Symbol {
+                    name: demangle(label),
+                    line: index,
+                    end: asm.len(),
+                    instructions: 0,
+                });
+            }
+            continue;
+        }
+
+        if let Some(last) = symbols.last_mut() {
+            if !text.starts_with('.') && line.text.starts_with(char::is_whitespace) {
+                last.instructions += 1;
+            }
+        }
+    }
+    symbols
+}
+
+/// Demangles Itanium C++ names. Labels are already demangled unless the demangle filter is off.
+fn demangle(label: &str) -> String {
+    if !label.starts_with("_Z") {
+        return label.to_owned();
+    }
+    cpp_demangle::Symbol::new(label)
+        .ok()
+        .and_then(|symbol| symbol.demangle(&Default::default()).ok())
+        .unwrap_or_else(|| label.to_owned())
+}
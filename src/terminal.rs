@@ -0,0 +1,104 @@
+use crossterm::{
+    cursor::Show,
+    event::{DisableMouseCapture, EnableMouseCapture},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use tui::{backend::CrosstermBackend, Terminal};
+
+use std::io::Stdout;
+use std::ops::{Deref, DerefMut};
+
+/// Owns the terminal while the TUI is running.
+///
+/// Raw mode, the alternate screen and mouse capture are enabled on creation and always restored when the
+/// session is dropped, be it on the happy path, an early return or a panic.
+pub struct TerminalSession {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+}
+
+impl TerminalSession {
+    pub fn new() -> Result<Self, std::io::Error> {
+        install_panic_hook();
+
+        take_over()?;
+        let backend = CrosstermBackend::new(std::io::stdout());
+        match Terminal::new(backend) {
+            Ok(terminal) => Ok(Self { terminal }),
+            Err(e) => {
+                restore();
+                Err(e)
+            }
+        }
+    }
+
+    /// Hands the terminal back to the shell while `f` runs, e.g. to launch an editor, and takes
+    /// it over again afterwards.
+    pub fn suspend<T>(&mut self, f: impl FnOnce() -> T) -> Result<T, std::io::Error> {
+        restore();
+        let result = f();
+        take_over()?;
+        // Whatever ran meanwhile drew over the screen
+        self.terminal.clear()?;
+        Ok(result)
+    }
+}
+
+impl Deref for TerminalSession {
+    type Target = Terminal<CrosstermBackend<Stdout>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.terminal
+    }
+}
+
+impl DerefMut for TerminalSession {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.terminal
+    }
+}
+
+impl Drop for TerminalSession {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+fn take_over() -> Result<(), std::io::Error> {
+    enable_raw_mode()?;
+    if let Err(e) = execute!(std::io::stdout(), EnterAlternateScreen, EnableMouseCapture) {
+        restore();
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Puts the terminal back in the state the shell expects. Errors are ignored since this runs
+/// while tearing down and there is nothing better to do with them.
+fn restore() {
+    let _ = disable_raw_mode();
+    let _ = execute!(
+        std::io::stdout(),
+        DisableMouseCapture,
+        LeaveAlternateScreen,
+        Show
+    );
+}
+
+/// Restores the terminal before the default hook prints the panic message, otherwise the
+/// message ends up on the alternate screen and is lost.
+///
+/// The process exits afterwards: a panic in a spawned task would otherwise only end that task,
+/// leaving the UI drawing over a terminal that was handed back to the shell.
+fn install_panic_hook() {
+    static INSTALL: std::sync::Once = std::sync::Once::new();
+    INSTALL.call_once(|| {
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            restore();
+            default_hook(info);
+            // Same status as an unwinding panic in the main thread
+            std::process::exit(101);
+        }));
+    });
+}
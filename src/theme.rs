@@ -0,0 +1,211 @@
+//! Colors of the TUI.
+//!
+//! A theme starts from a preset and replaces any of its colors by name. Colors are written as
+//! names (`light_blue`, `dark_gray`), `#rrggbb` or a 256 color palette index.
+//!
+//! ```toml
+//! [theme]
+//! preset = "light"
+//! title = "blue"
+//! cursor = "#dddddd"
+//! ```
+
+use crate::highlight::TokenKind;
+
+use serde_derive::Deserialize;
+use tui::style::Color;
+
+use std::collections::HashMap;
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Unknown theme color {0:?}")]
+    UnknownColorName(String),
+    #[error("Invalid color {1:?} for {0}")]
+    InvalidColor(String, String),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Preset {
+    /// For terminals with a dark background
+    #[default]
+    Dark,
+    /// For terminals with a light background
+    Light,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub border: Color,
+    pub selected_border: Color,
+    pub title: Color,
+    /// Background of the cursor line
+    pub cursor: Color,
+    /// Background of the visual selection
+    pub selection: Color,
+    /// Background of search matches
+    pub search_match: Color,
+    /// Background of the search match last jumped to
+    pub search_current: Color,
+    /// Secondary text: addresses, line numbers, placeholders
+    pub dim: Color,
+    pub opcodes: Color,
+    /// Lines added by an optimization pass and passed optimizations
+    pub added: Color,
+    /// Missed optimizations
+    pub removed: Color,
+    /// Optimization analysis remarks
+    pub warning: Color,
+    pub error: Color,
+    pub mnemonic: Color,
+    pub register: Color,
+    pub immediate: Color,
+    pub label: Color,
+    pub comment: Color,
+    pub directive: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::preset(Preset::Dark)
+    }
+}
+
+impl Theme {
+    pub fn preset(preset: Preset) -> Self {
+        match preset {
+            Preset::Dark => Self {
+                border: Color::Reset,
+                selected_border: Color::Reset,
+                title: Color::Magenta,
+                cursor: Color::DarkGray,
+                selection: Color::Blue,
+                search_match: Color::Yellow,
+                search_current: Color::LightRed,
+                dim: Color::DarkGray,
+                opcodes: Color::Cyan,
+                added: Color::Green,
+                removed: Color::Red,
+                warning: Color::Yellow,
+                error: Color::Red,
+                mnemonic: Color::LightBlue,
+                register: Color::LightMagenta,
+                immediate: Color::LightGreen,
+                label: Color::Yellow,
+                comment: Color::DarkGray,
+                directive: Color::Magenta,
+            },
+            Preset::Light => Self {
+                border: Color::Reset,
+                selected_border: Color::Reset,
+                title: Color::Blue,
+                cursor: Color::Rgb(0xdd, 0xdd, 0xdd),
+                selection: Color::Rgb(0xc6, 0xdb, 0xf0),
+                search_match: Color::Rgb(0xff, 0xe0, 0x66),
+                search_current: Color::Rgb(0xff, 0x99, 0x66),
+                dim: Color::Gray,
+                opcodes: Color::Rgb(0x00, 0x80, 0x80),
+                added: Color::Rgb(0x00, 0x80, 0x00),
+                removed: Color::Rgb(0xc0, 0x00, 0x00),
+                warning: Color::Rgb(0xa0, 0x60, 0x00),
+                error: Color::Red,
+                mnemonic: Color::Blue,
+                register: Color::Magenta,
+                immediate: Color::Rgb(0x00, 0x80, 0x00),
+                label: Color::Rgb(0xa0, 0x60, 0x00),
+                comment: Color::Gray,
+                directive: Color::Rgb(0x80, 0x00, 0x80),
+            },
+        }
+    }
+
+    /// `preset` with the colors in `colors` replaced, keyed by field name.
+    pub fn new(preset: Preset, colors: &HashMap<String, String>) -> Result<Self, Error> {
+        let mut theme = Self::preset(preset);
+        for (name, value) in colors {
+            let color = parse_color(value)
+                .ok_or_else(|| Error::InvalidColor(name.clone(), value.clone()))?;
+            *theme
+                .color_mut(name)
+                .ok_or_else(|| Error::UnknownColorName(name.clone()))? = color;
+        }
+        Ok(theme)
+    }
+
+    fn color_mut(&mut self, name: &str) -> Option<&mut Color> {
+        Some(match name {
+            "border" => &mut self.border,
+            "selected_border" => &mut self.selected_border,
+            "title" => &mut self.title,
+            "cursor" => &mut self.cursor,
+            "selection" => &mut self.selection,
+            "search_match" => &mut self.search_match,
+            "search_current" => &mut self.search_current,
+            "dim" => &mut self.dim,
+            "opcodes" => &mut self.opcodes,
+            "added" => &mut self.added,
+            "removed" => &mut self.removed,
+            "warning" => &mut self.warning,
+            "error" => &mut self.error,
+            "mnemonic" => &mut self.mnemonic,
+            "register" => &mut self.register,
+            "immediate" => &mut self.immediate,
+            "label" => &mut self.label,
+            "comment" => &mut self.comment,
+            "directive" => &mut self.directive,
+            _ => return None,
+        })
+    }
+
+    /// Color of an assembly token, `None` for plain text.
+    pub fn token(&self, kind: TokenKind) -> Option<Color> {
+        match kind {
+            TokenKind::Mnemonic => Some(self.mnemonic),
+            TokenKind::Register => Some(self.register),
+            TokenKind::Immediate => Some(self.immediate),
+            TokenKind::Label => Some(self.label),
+            TokenKind::Comment => Some(self.comment),
+            TokenKind::Directive => Some(self.directive),
+            TokenKind::Text => None,
+        }
+    }
+}
+
+pub fn parse_color(value: &str) -> Option<Color> {
+    const NAMED: &[(&str, Color)] = &[
+        ("reset", Color::Reset),
+        ("black", Color::Black),
+        ("red", Color::Red),
+        ("green", Color::Green),
+        ("yellow", Color::Yellow),
+        ("blue", Color::Blue),
+        ("magenta", Color::Magenta),
+        ("cyan", Color::Cyan),
+        ("gray", Color::Gray),
+        ("dark_gray", Color::DarkGray),
+        ("light_red", Color::LightRed),
+        ("light_green", Color::LightGreen),
+        ("light_yellow", Color::LightYellow),
+        ("light_blue", Color::LightBlue),
+        ("light_magenta", Color::LightMagenta),
+        ("light_cyan", Color::LightCyan),
+        ("white", Color::White),
+    ];
+
+    let value = value.trim().to_lowercase();
+    if let Some(hex) = value.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |index: usize| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok();
+        return Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?));
+    }
+    if let Ok(index) = value.parse::<u8>() {
+        return Some(Color::Indexed(index));
+    }
+    NAMED
+        .iter()
+        .find(|(name, _)| *name == value.replace(['-', ' '], "_"))
+        .map(|(_, color)| *color)
+}
@@ -1,300 +1,2011 @@
-use crate::compiler_explorer::CompilationResult;
+use crate::compiler_explorer::{
+    AsmOutput, CompilationResult, GccDumpPass, InstructionDocs, OptPipelinePass, OptRemark,
+    ProduceOptions, RemarkKind, StreamOutput,
+};
+use crate::editor::{Buffer, Motion};
+use crate::keymap::{Action, Key, Keymap, Lookup};
+use crate::layout::{self, Layout, Node, Pane, Side};
+use crate::theme::Theme;
+use crate::{cfg, highlight, outline};
+
+mod search;
+
+use search::{line_text, Direction, Prompt, Search};
 
 use ansi_to_tui::ansi_to_text;
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use regex::Regex;
 use tui::{
     backend::Backend,
-    style::{Color, Modifier, Style},
-    text::{Span, Text},
-    widgets::{Block, BorderType, Borders, Paragraph, Wrap},
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Span, Spans, Text},
+    widgets::{Block, BorderType, Borders, Clear, Paragraph, Wrap},
     Terminal,
 };
 
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+use std::time::{Duration, Instant};
+
+/// Percent of its split a pane grows or shrinks by each time it is resized
+const RESIZE_STEP: i32 = 5;
+/// Lines scrolled by each step of the mouse wheel
+const MOUSE_SCROLL: u16 = 3;
+/// Longest time between the clicks of a double click
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
 #[derive(PartialEq, Debug, Clone, Copy)]
 enum Widgets {
     Asm = 0,
     Stdout,
     Stderr,
+    Ir,
+    Ast,
+    GccDump,
+    OptPipeline,
+    Source,
+    Editor,
+    Remarks,
+    Cfg,
+    Outline,
 }
 
-#[derive(Clone, Copy)]
-struct WidgetConfig {
-    vertical_offset: u16,
-    horizontal_offset: u16,
+impl Widgets {
+    const COUNT: usize = 12;
+
+    /// Optional widgets are only shown after the user asks for them, and their output is only
+    /// requested from the compiler while they are shown.
+    fn is_optional(self) -> bool {
+        !matches!(self, Widgets::Asm | Widgets::Stdout | Widgets::Stderr)
+    }
 }
 
-impl Default for WidgetConfig {
-    fn default() -> Self {
-        Self {
-            vertical_offset: 0,
-            horizontal_offset: 0,
+impl From<Pane> for Widgets {
+    fn from(pane: Pane) -> Self {
+        match pane {
+            Pane::Asm => Widgets::Asm,
+            Pane::Stdout => Widgets::Stdout,
+            Pane::Stderr => Widgets::Stderr,
+            Pane::Ir => Widgets::Ir,
+            Pane::Ast => Widgets::Ast,
+            Pane::GccDump => Widgets::GccDump,
+            Pane::OptPipeline => Widgets::OptPipeline,
+            Pane::Source => Widgets::Source,
+            Pane::Editor => Widgets::Editor,
+            Pane::Remarks => Widgets::Remarks,
+            Pane::Cfg => Widgets::Cfg,
+            Pane::Outline => Widgets::Outline,
+        }
+    }
+}
+
+impl From<Widgets> for Pane {
+    fn from(widget: Widgets) -> Self {
+        match widget {
+            Widgets::Asm => Pane::Asm,
+            Widgets::Stdout => Pane::Stdout,
+            Widgets::Stderr => Pane::Stderr,
+            Widgets::Ir => Pane::Ir,
+            Widgets::Ast => Pane::Ast,
+            Widgets::GccDump => Pane::GccDump,
+            Widgets::OptPipeline => Pane::OptPipeline,
+            Widgets::Source => Pane::Source,
+            Widgets::Editor => Pane::Editor,
+            Widgets::Remarks => Pane::Remarks,
+            Widgets::Cfg => Pane::Cfg,
+            Widgets::Outline => Pane::Outline,
         }
     }
 }
 
+/// Requests from the UI that need to be handled by the caller.
 #[derive(Debug, PartialEq)]
-pub enum Orientation {
-    Vertical,
-    Horizontal,
+pub enum Command {
+    /// The outputs requested from the compiler for the file of a tab changed, see
+    /// `Ui::produce`, or the user asked for a new compilation
+    Recompile(usize),
+    /// Fetch the documentation of an instruction and pass it to `Ui::set_docs`
+    InstructionDocs(String),
+    /// Copy the text to the clipboard
+    Yank(String),
+    /// Write the layout to the configuration file
+    SaveLayout(Layout),
+    /// The text in the editor pane of a tab changed, compile it once the typing stops
+    Edit(usize, String),
+    /// Open the file of a tab in an external editor, at a line of the source when known
+    OpenInEditor(usize, Option<i64>),
+    Quit,
+}
+
+#[derive(Clone, Copy, Default)]
+struct WidgetConfig {
+    vertical_offset: usize,
+    horizontal_offset: u16,
+    /// Line under the cursor, kept in view when it moves
+    cursor: usize,
+    /// Other end of the visual selection, which always extends to the cursor
+    anchor: Option<usize>,
+}
+
+impl WidgetConfig {
+    /// Lines of the visual selection, or the cursor line when not selecting.
+    fn selection(&self) -> RangeInclusive<usize> {
+        let anchor = self.anchor.unwrap_or(self.cursor);
+        anchor.min(self.cursor)..=anchor.max(self.cursor)
+    }
+}
+
+/// Popup drawn over the panes, which takes the keys until it is closed.
+struct Popup {
+    kind: PopupKind,
+    scroll: u16,
+}
+
+enum PopupKind {
+    /// Documentation of the instruction under the cursor, unset while it is being fetched
+    Docs {
+        opcode: String,
+        docs: Option<Result<InstructionDocs, String>>,
+    },
+    Help,
 }
 
 pub struct Ui {
     selected_widget: Widgets,
-    widget_config: [WidgetConfig; 3],
     focus: Option<Widgets>,
-    orientation: Orientation,
+    /// Arrangement of every widget but the outline
+    layout: Node,
+    /// One for each file, with its own compilation and widget state
+    tabs: Vec<Tab>,
+    /// Index of the tab shown
+    current: usize,
+    show_addresses: bool,
+    show_opcodes: bool,
+    /// Widgets the user wants shown. The ASM, stdout and stderr widgets also need content.
+    enabled_widgets: Vec<Widgets>,
+    /// Only optimization remarks of this kind are shown when set
+    remark_filter: Option<RemarkKind>,
+    prompt: Option<Prompt>,
+    /// Keys are typed into the editor pane
+    inserting: bool,
+    popup: Option<Popup>,
+    /// Shown in the bottom line until the next key
+    status: Option<String>,
+    keymap: Keymap,
+    theme: Theme,
+    /// Keys pressed so far of a sequence bound to an action
+    pending_keys: Vec<Key>,
+    /// Where each widget was last drawn, empty when it was not
+    areas: Cell<[Rect; Widgets::COUNT]>,
+    /// Area the layout was last drawn in
+    layout_area: Cell<Rect>,
+    /// Last click, to tell double clicks apart
+    last_click: Option<(Widgets, Instant)>,
+    /// Border being dragged with the mouse: the widget, its side and how far the pointer is
+    /// from the edge
+    drag: Option<(Widgets, Side, i32)>,
+}
+
+/// A file being compiled, and the state of the widgets showing it.
+struct Tab {
+    /// Shown in the tab bar
+    name: String,
+    widget_config: [WidgetConfig; Widgets::COUNT],
     data: Option<CompilationResult>,
+    error: Option<String>,
+    source: Option<String>,
+    /// Source in the editor pane, where the cursor row follows the cursor of the widget
+    editor: Buffer,
+    gcc_dump_pass: Option<GccDumpPass>,
+    /// Index into the passes of the optimization pipeline that changed the IR
+    opt_pipeline_pass: usize,
+    /// Function shown in the control flow graph widget, the first one when unset
+    cfg_function: Option<String>,
+    /// Index of the function selected in the outline
+    outline_selected: usize,
+    /// Names of the functions whose body is hidden in the ASM widget
+    folded: HashSet<String>,
+    /// Last search, with the widget it searches
+    search: Option<(Widgets, Search)>,
+    /// Compiled before the widgets shown last changed, so some of their output may be missing
+    stale: bool,
+}
+
+impl Tab {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            widget_config: [WidgetConfig::default(); Widgets::COUNT],
+            data: None,
+            error: None,
+            source: None,
+            editor: Buffer::default(),
+            gcc_dump_pass: None,
+            opt_pipeline_pass: 0,
+            cfg_function: None,
+            outline_selected: 0,
+            folded: HashSet::new(),
+            search: None,
+            stale: false,
+        }
+    }
+}
+
+/// A row of the ASM widget.
+enum AsmRow {
+    Line(usize),
+    /// Placeholder for the body of a folded function, with its number of instructions
+    Folded(usize),
 }
 
 impl Ui {
-    pub fn new(orientation: Orientation) -> Self {
+    /// `tabs` names the files to show, one tab each.
+    pub fn new(tabs: Vec<String>, layout: Layout, keymap: Keymap, theme: Theme) -> Self {
+        // Until the first key press, point to the help
+        let status = keymap
+            .sequences(Action::Help)
+            .first()
+            .map(|keys| format!("{}: help", keys));
         Self {
             selected_widget: Widgets::Asm,
-            widget_config: [WidgetConfig::default(); 3],
             focus: None,
-            orientation,
-            data: None,
+            layout: layout.root,
+            tabs: tabs.into_iter().map(Tab::new).collect(),
+            current: 0,
+            show_addresses: true,
+            show_opcodes: true,
+            enabled_widgets: layout.shown.into_iter().map(Widgets::from).collect(),
+            remark_filter: None,
+            prompt: None,
+            inserting: false,
+            popup: None,
+            status,
+            keymap,
+            theme,
+            pending_keys: vec![],
+            areas: Cell::new([Rect::default(); Widgets::COUNT]),
+            layout_area: Cell::new(Rect::default()),
+            last_click: None,
+            drag: None,
         }
     }
 
-    pub fn set_data(&mut self, compilation: CompilationResult) {
-        self.data = Some(compilation);
-        // Reset offsets
-        self.widget_config
+    fn tab(&self) -> &Tab {
+        &self.tabs[self.current]
+    }
+
+    fn tab_mut(&mut self) -> &mut Tab {
+        &mut self.tabs[self.current]
+    }
+
+    /// Sets the source being compiled in `tab`, shown in the source widget.
+    pub fn set_source(&mut self, tab: usize, source: String) {
+        let tab = &mut self.tabs[tab];
+        tab.editor.set_text(&source);
+        tab.source = Some(source);
+    }
+
+    pub fn set_data(&mut self, tab: usize, compilation: CompilationResult) {
+        let tab = &mut self.tabs[tab];
+        tab.data = Some(compilation);
+        tab.error = None;
+        if let Some((_, search)) = &mut tab.search {
+            search.current = None;
+        }
+        // Reset offsets, but for the editor, whose text does not come from the compilation
+        tab.widget_config
             .iter_mut()
-            .for_each(|config| config.vertical_offset = 0);
+            .enumerate()
+            .filter(|(index, _)| *index != Widgets::Editor as usize)
+            .for_each(|(_, config)| {
+                config.vertical_offset = 0;
+                config.cursor = 0;
+                config.anchor = None;
+            });
+    }
+
+    /// Shows `error` in a banner on top of the last good result of `tab` until new data is set.
+    pub fn set_error(&mut self, tab: usize, error: String) {
+        self.tabs[tab].error = Some(error);
+    }
+
+    /// Shows `status` in the bottom line until the next key is pressed.
+    pub fn set_status(&mut self, status: String) {
+        self.status = Some(status);
+    }
+
+    /// Shows the documentation requested with `Command::InstructionDocs`, unless the popup was
+    /// closed or opened for another instruction in the meantime.
+    pub fn set_docs(&mut self, opcode: &str, docs: Result<InstructionDocs, String>) {
+        if let Some(Popup {
+            kind:
+                PopupKind::Docs {
+                    opcode: shown,
+                    docs: shown_docs,
+                },
+            ..
+        }) = &mut self.popup
+        {
+            if shown == opcode {
+                *shown_docs = Some(docs);
+            }
+        }
+    }
+
+    /// Outputs the compiler has to produce for the widgets being shown, when compiling the file
+    /// of `tab`.
+    pub fn produce(&self, tab: usize) -> ProduceOptions {
+        let enabled = |widget| self.enabled_widgets.contains(&widget);
+        ProduceOptions {
+            ir: enabled(Widgets::Ir),
+            ast: enabled(Widgets::Ast),
+            gcc_dump: enabled(Widgets::GccDump),
+            gcc_dump_pass: self.tabs[tab].gcc_dump_pass.clone(),
+            opt_pipeline: enabled(Widgets::OptPipeline),
+            opt_info: enabled(Widgets::Source) || enabled(Widgets::Remarks),
+        }
     }
 
     pub fn handle_key_event<B: Backend>(
         &mut self,
         event: KeyEvent,
         terminal: &mut Terminal<B>,
+    ) -> Result<Option<Command>, std::io::Error> {
+        self.status = None;
+        if self.prompt.is_some() {
+            self.handle_prompt_key(event.code);
+            self.draw(terminal)?;
+            return Ok(None);
+        }
+        // The editor may have been hidden or left with the mouse
+        self.inserting &=
+            self.selected_widget == Widgets::Editor && self.is_visible(Widgets::Editor);
+        if self.inserting {
+            let command = self.handle_insert_key(event);
+            self.draw(terminal)?;
+            return Ok(command);
+        }
+
+        let key = Key::from(event);
+        self.pending_keys.push(key);
+        let action = match self.keymap.lookup(&self.pending_keys) {
+            Lookup::Action(action) => Some(action),
+            Lookup::Pending => return Ok(None),
+            // Start over from the last key when it does not continue the sequence
+            Lookup::None if self.pending_keys.len() > 1 => {
+                self.pending_keys = vec![key];
+                match self.keymap.lookup(&self.pending_keys) {
+                    Lookup::Action(action) => Some(action),
+                    Lookup::Pending => return Ok(None),
+                    Lookup::None => None,
+                }
+            }
+            Lookup::None => None,
+        };
+        self.pending_keys.clear();
+
+        if let Some(popup) = &mut self.popup {
+            match action {
+                Some(Action::ScrollDown) => popup.scroll += 1,
+                Some(Action::ScrollUp) => popup.scroll = popup.scroll.saturating_sub(1),
+                _ => self.popup = None,
+            }
+            self.draw(terminal)?;
+            return Ok(None);
+        }
+
+        let command = match action {
+            Some(action) => self.perform(action),
+            None => return Ok(None),
+        };
+        self.draw(terminal)?;
+        Ok(command)
+    }
+
+    pub fn handle_mouse_event<B: Backend>(
+        &mut self,
+        event: MouseEvent,
+        terminal: &mut Terminal<B>,
     ) -> Result<(), std::io::Error> {
-        let KeyEvent { code, modifiers } = event;
-        let update_ui = match (code, modifiers) {
-            (KeyCode::Char('j'), KeyModifiers::NONE) => {
-                let config = match self.selected_widget {
-                    Widgets::Asm => &mut self.widget_config[0],
-                    Widgets::Stdout => &mut self.widget_config[1],
-                    Widgets::Stderr => &mut self.widget_config[2],
-                };
-                config.vertical_offset += 1;
-                true
+        if self.prompt.is_some() {
+            return Ok(());
+        }
+        let (column, row) = (event.column, event.row);
+        if let Some(popup) = &mut self.popup {
+            match event.kind {
+                MouseEventKind::ScrollDown => popup.scroll += MOUSE_SCROLL,
+                MouseEventKind::ScrollUp => {
+                    popup.scroll = popup.scroll.saturating_sub(MOUSE_SCROLL)
+                }
+                MouseEventKind::Down(_) => self.popup = None,
+                _ => return Ok(()),
             }
-            (KeyCode::Char('k'), KeyModifiers::NONE) => {
-                let config = match self.selected_widget {
-                    Widgets::Asm => &mut self.widget_config[0],
-                    Widgets::Stdout => &mut self.widget_config[1],
-                    Widgets::Stderr => &mut self.widget_config[2],
+            return self.draw(terminal);
+        }
+
+        match event.kind {
+            MouseEventKind::Down(MouseButton::Left) => self.click(column, row),
+            MouseEventKind::Drag(MouseButton::Left) => {
+                if !self.drag_edge(column, row) {
+                    return Ok(());
+                }
+            }
+            MouseEventKind::Up(MouseButton::Left) => {
+                self.drag = None;
+                return Ok(());
+            }
+            MouseEventKind::ScrollDown | MouseEventKind::ScrollUp => {
+                let widget = match self.widget_at(column, row) {
+                    Some(widget) => widget,
+                    None => return Ok(()),
+                };
+                let step = match event.kind {
+                    MouseEventKind::ScrollDown => MOUSE_SCROLL as i16,
+                    _ => -(MOUSE_SCROLL as i16),
                 };
-                if config.vertical_offset > 0 {
-                    config.vertical_offset -= 1;
+                if event.modifiers.contains(KeyModifiers::SHIFT) {
+                    let config = &mut self.tab_mut().widget_config[widget as usize];
+                    config.horizontal_offset = config.horizontal_offset.saturating_add_signed(step);
+                } else {
+                    self.scroll(widget, step as isize);
                 }
-                true
             }
-            (KeyCode::Char('l'), KeyModifiers::NONE) => {
-                let config = match self.selected_widget {
-                    Widgets::Asm => &mut self.widget_config[0],
-                    Widgets::Stdout => &mut self.widget_config[1],
-                    Widgets::Stderr => &mut self.widget_config[2],
+            _ => return Ok(()),
+        }
+        self.draw(terminal)
+    }
+
+    fn widget_at(&self, column: u16, row: u16) -> Option<Widgets> {
+        let areas = self.areas.get();
+        self.visible_widgets().into_iter().find(|widget| {
+            let area = areas[*widget as usize];
+            (area.left()..area.right()).contains(&column)
+                && (area.top()..area.bottom()).contains(&row)
+        })
+    }
+
+    /// Selects the widget under the pointer, or focuses it on a double click. Clicking its border
+    /// starts dragging it.
+    fn click(&mut self, column: u16, row: u16) {
+        self.status = None;
+        let widget = match self.widget_at(column, row) {
+            Some(widget) => widget,
+            None => return,
+        };
+        let now = Instant::now();
+        let double = self
+            .last_click
+            .is_some_and(|(last, time)| last == widget && now.duration_since(time) < DOUBLE_CLICK);
+        self.last_click = (!double).then_some((widget, now));
+        self.selected_widget = widget;
+        if double {
+            self.drag = None;
+            self.perform(Action::ToggleFocus);
+            return;
+        }
+
+        let area = self.areas.get()[widget as usize];
+        let side = if column == area.left() {
+            Some(Side::Left)
+        } else if column + 1 == area.right() {
+            Some(Side::Right)
+        } else if row == area.top() {
+            Some(Side::Up)
+        } else if row + 1 == area.bottom() {
+            Some(Side::Down)
+        } else {
+            None
+        };
+        self.drag = side
+            .filter(|side| self.neighbor(*side).is_some())
+            .map(|side| {
+                let edge = match side {
+                    Side::Left | Side::Up => side.start(area),
+                    Side::Right | Side::Down => side.end(area),
                 };
-                config.horizontal_offset += 1;
-                true
+                (
+                    widget,
+                    side,
+                    edge as i32 - side.position(column, row) as i32,
+                )
+            });
+    }
+
+    /// Moves the border being dragged to the pointer. Returns whether the layout changed.
+    fn drag_edge(&mut self, column: u16, row: u16) -> bool {
+        let (widget, side, offset) = match self.drag {
+            Some(drag) => drag,
+            None => return false,
+        };
+        let edge = (side.position(column, row) as i32 + offset).max(0) as u16;
+        let mut layout = self.layout.clone();
+        let moved = layout.move_edge(
+            self.layout_area.get(),
+            widget.into(),
+            side,
+            edge,
+            &self.pane_visible(),
+        );
+        self.layout = layout;
+        moved
+    }
+
+    /// Why `action` does nothing in the current state, if it does not.
+    fn unavailable(&self, action: Action) -> Option<&'static str> {
+        let widget = self.selected_widget;
+        match action {
+            Action::NextPane
+            | Action::PreviousPane
+            | Action::PaneLeft
+            | Action::PaneRight
+            | Action::PaneUp
+            | Action::PaneDown
+            | Action::GrowPane
+            | Action::ShrinkPane
+                if self.focus.is_some() =>
+            {
+                Some("a pane is focused")
+            }
+            _ if pane_side(action).is_some_and(|side| self.neighbor(side).is_none()) => {
+                Some("no pane on that side")
+            }
+            Action::GrowPane | Action::ShrinkPane if widget == Widgets::Outline => {
+                Some("not in the outline")
+            }
+            Action::GrowPane | Action::ShrinkPane
+                if !self
+                    .layout
+                    .clone()
+                    .resize(widget.into(), 0, &self.pane_visible()) =>
+            {
+                Some("no other pane")
+            }
+            Action::Fold if !matches!(widget, Widgets::Asm | Widgets::Outline) => {
+                Some("ASM pane and outline")
+            }
+            Action::PreviousEntry | Action::NextEntry
+                if !matches!(
+                    widget,
+                    Widgets::GccDump | Widgets::OptPipeline | Widgets::Cfg
+                ) =>
+            {
+                Some("GCC dump, opt pipeline and CFG panes")
             }
-            (KeyCode::Char('h'), KeyModifiers::NONE) => {
-                let config = match self.selected_widget {
-                    Widgets::Asm => &mut self.widget_config[0],
-                    Widgets::Stdout => &mut self.widget_config[1],
-                    Widgets::Stderr => &mut self.widget_config[2],
+            Action::InstructionDocs if widget != Widgets::Asm => Some("ASM pane"),
+            Action::NextTab | Action::PreviousTab if self.tabs.len() < 2 => Some("only one file"),
+            Action::VisualSelection if widget == Widgets::Outline => Some("not in the outline"),
+            Action::NextMatch | Action::PreviousMatch if self.tab().search.is_none() => {
+                Some("no search")
+            }
+            _ => None,
+        }
+    }
+
+    fn perform(&mut self, action: Action) -> Option<Command> {
+        if self.unavailable(action).is_some() {
+            return None;
+        }
+        let page = self.height(self.selected_widget) as isize;
+        match action {
+            Action::ScrollDown => self.move_cursor(1),
+            Action::ScrollUp => self.move_cursor(-1),
+            Action::ScrollRight if self.selected_widget == Widgets::Editor => {
+                self.move_in_editor(Motion::Right)
+            }
+            Action::ScrollLeft if self.selected_widget == Widgets::Editor => {
+                self.move_in_editor(Motion::Left)
+            }
+            Action::ScrollRight => self.selected_config().horizontal_offset += 1,
+            Action::ScrollLeft => {
+                let config = self.selected_config();
+                config.horizontal_offset = config.horizontal_offset.saturating_sub(1);
+            }
+            Action::Top => self.set_cursor(0),
+            Action::Bottom => self.set_cursor(usize::MAX),
+            Action::HalfPageDown => self.move_cursor(page / 2),
+            Action::HalfPageUp => self.move_cursor(-page / 2),
+            Action::PageDown => self.move_cursor(page),
+            Action::PageUp => self.move_cursor(-page),
+            Action::NextPane => self.select_next_widget(1),
+            Action::PreviousPane => self.select_next_widget(-1),
+            Action::PaneLeft | Action::PaneRight | Action::PaneUp | Action::PaneDown => {
+                if let Some(widget) = pane_side(action).and_then(|side| self.neighbor(side)) {
+                    self.selected_widget = widget;
+                }
+            }
+            Action::ToggleFocus => {
+                self.focus = match self.focus {
+                    None => Some(self.selected_widget),
+                    Some(_) => None,
                 };
-                if config.horizontal_offset > 0 {
-                    config.horizontal_offset -= 1;
+            }
+            Action::GrowPane => self.resize_selected(RESIZE_STEP),
+            Action::ShrinkPane => self.resize_selected(-RESIZE_STEP),
+            Action::ToggleOrientation => self.layout.direction = self.layout.direction.toggled(),
+            Action::HidePane => return self.toggle_widget(self.selected_widget),
+            Action::ShowPanes => {
+                for widget in [Widgets::Asm, Widgets::Stdout, Widgets::Stderr] {
+                    if !self.enabled_widgets.contains(&widget) {
+                        self.enabled_widgets.push(widget);
+                    }
                 }
-                true
             }
-            (KeyCode::Char('J'), KeyModifiers::SHIFT)
-                if self.focus.is_none() && self.orientation == Orientation::Vertical =>
-            {
-                self.selected_widget = match self.selected_widget {
-                    Widgets::Asm => Widgets::Stdout,
-                    Widgets::Stdout => Widgets::Stderr,
-                    Widgets::Stderr => Widgets::Asm,
+            Action::SaveLayout => {
+                return Some(Command::SaveLayout(Layout {
+                    shown: self
+                        .enabled_widgets
+                        .iter()
+                        .map(|w| Pane::from(*w))
+                        .collect(),
+                    root: self.layout.clone(),
+                }))
+            }
+            Action::Quit => {
+                let config = self.selected_config();
+                if config.anchor.is_none() {
+                    return Some(Command::Quit);
+                }
+                config.anchor = None;
+            }
+            Action::Recompile => return Some(Command::Recompile(self.current)),
+            Action::NextTab => return self.select_next_tab(1),
+            Action::PreviousTab => return self.select_next_tab(-1),
+            Action::ToggleAddresses => self.show_addresses = !self.show_addresses,
+            Action::ToggleOpcodes => self.show_opcodes = !self.show_opcodes,
+            Action::ToggleIr => return self.toggle_widget(Widgets::Ir),
+            Action::ToggleAst => return self.toggle_widget(Widgets::Ast),
+            Action::ToggleGccDump => return self.toggle_widget(Widgets::GccDump),
+            Action::ToggleOptPipeline => return self.toggle_widget(Widgets::OptPipeline),
+            Action::ToggleSource => return self.toggle_widget(Widgets::Source),
+            Action::ToggleRemarks => return self.toggle_widget(Widgets::Remarks),
+            Action::ToggleCfg => return self.toggle_widget(Widgets::Cfg),
+            Action::ToggleOutline => return self.toggle_widget(Widgets::Outline),
+            Action::ToggleEditor => return self.toggle_widget(Widgets::Editor),
+            Action::OpenInEditor => {
+                return Some(Command::OpenInEditor(
+                    self.current,
+                    self.asm_cursor_source_line(),
+                ))
+            }
+            Action::Edit => {
+                if !self.enabled_widgets.contains(&Widgets::Editor) {
+                    self.enabled_widgets.push(Widgets::Editor);
+                }
+                self.selected_widget = Widgets::Editor;
+                self.inserting = true;
+                // Start typing where the cursor of the pane was moved to
+                let row = self.tab().widget_config[Widgets::Editor as usize].cursor;
+                let editor = &mut self.tab_mut().editor;
+                editor.set_cursor(row, editor.cursor().1);
+            }
+            Action::CycleRemarkFilter => {
+                self.remark_filter = match self.remark_filter {
+                    None => Some(RemarkKind::Passed),
+                    Some(RemarkKind::Passed) => Some(RemarkKind::Missed),
+                    Some(RemarkKind::Missed) => Some(RemarkKind::Analysis),
+                    Some(RemarkKind::Analysis) => None,
                 };
-                true
             }
-            (KeyCode::Char('L'), KeyModifiers::SHIFT)
-                if self.focus.is_none() && self.orientation == Orientation::Horizontal =>
-            {
-                self.selected_widget = match self.selected_widget {
-                    Widgets::Asm => Widgets::Stdout,
-                    Widgets::Stdout => Widgets::Stderr,
-                    Widgets::Stderr => Widgets::Asm,
+            Action::PreviousEntry => return self.select_next_entry(-1),
+            Action::NextEntry => return self.select_next_entry(1),
+            Action::SearchForward => self.start_search(Direction::Forward),
+            Action::SearchBackward => self.start_search(Direction::Backward),
+            Action::NextMatch => self.next_match(false),
+            Action::PreviousMatch => self.next_match(true),
+            Action::Fold => self.toggle_fold(),
+            Action::FoldAll => self.toggle_fold_all(),
+            Action::VisualSelection => {
+                let config = self.selected_config();
+                config.anchor = match config.anchor {
+                    Some(_) => None,
+                    None => Some(config.cursor),
                 };
+            }
+            Action::Yank => return self.yank(false),
+            Action::YankAll => return self.yank(true),
+            Action::InstructionDocs => return self.show_docs(),
+            Action::Help => {
+                self.popup = Some(Popup {
+                    kind: PopupKind::Help,
+                    scroll: 0,
+                })
+            }
+        }
+        None
+    }
+
+    fn start_search(&mut self, direction: Direction) {
+        self.prompt = Some(Prompt {
+            direction,
+            input: String::new(),
+            error: None,
+            origin: *self.selected_config(),
+        });
+    }
+
+    fn handle_prompt_key(&mut self, code: KeyCode) {
+        let prompt = match &mut self.prompt {
+            Some(prompt) => prompt,
+            None => return,
+        };
+        match code {
+            KeyCode::Esc => {
+                let origin = prompt.origin;
+                self.prompt = None;
+                self.tab_mut().search = None;
+                *self.selected_config() = origin;
+                return;
+            }
+            KeyCode::Enter => {
+                self.prompt = None;
+                return;
+            }
+            KeyCode::Backspace => {
+                prompt.input.pop();
+            }
+            KeyCode::Char(c) => prompt.input.push(c),
+            _ => return,
+        }
+
+        // Search as the regex is typed
+        let origin = prompt.origin;
+        if prompt.input.is_empty() {
+            prompt.error = None;
+            self.tab_mut().search = None;
+            *self.selected_config() = origin;
+            return;
+        }
+        match Regex::new(&prompt.input) {
+            Ok(regex) => {
+                prompt.error = None;
+                let direction = prompt.direction;
+                self.tab_mut().search = Some((
+                    self.selected_widget,
+                    Search {
+                        regex,
+                        direction,
+                        current: None,
+                    },
+                ));
+                self.jump_to_match(origin.cursor, direction, false);
+            }
+            // Most likely incomplete, keep the last valid search
+            Err(e) => {
+                prompt.error = e.to_string().lines().last().map(|line| line.to_owned());
+            }
+        }
+    }
+
+    /// Types a key into the editor pane. Arrows and home/end move around, ctrl moves by words.
+    /// The buffer keeps its own cursor while typing, the one of the pane only follows it.
+    fn handle_insert_key(&mut self, event: KeyEvent) -> Option<Command> {
+        let editor = &mut self.tab_mut().editor;
+        let ctrl = event.modifiers.contains(KeyModifiers::CONTROL);
+        let motion = match event.code {
+            KeyCode::Esc => {
+                self.inserting = false;
+                return None;
+            }
+            KeyCode::Left if ctrl => Some(Motion::WordBackward),
+            KeyCode::Right if ctrl => Some(Motion::WordForward),
+            KeyCode::Left => Some(Motion::Left),
+            KeyCode::Right => Some(Motion::Right),
+            KeyCode::Up => Some(Motion::Up),
+            KeyCode::Down => Some(Motion::Down),
+            KeyCode::Home => Some(Motion::LineStart),
+            KeyCode::End => Some(Motion::LineEnd),
+            _ => None,
+        };
+        let edited = match (motion, event.code) {
+            (Some(motion), _) => {
+                editor.move_cursor(motion);
+                false
+            }
+            (None, KeyCode::Char(c)) if !ctrl => {
+                editor.insert(c);
                 true
             }
-            (KeyCode::Char('K'), KeyModifiers::SHIFT)
-                if self.focus.is_none() && self.orientation == Orientation::Vertical =>
-            {
-                self.selected_widget = match self.selected_widget {
-                    Widgets::Asm => Widgets::Stderr,
-                    Widgets::Stdout => Widgets::Asm,
-                    Widgets::Stderr => Widgets::Stdout,
-                };
+            (None, KeyCode::Enter) => {
+                editor.insert('\n');
                 true
             }
-            (KeyCode::Char('H'), KeyModifiers::SHIFT)
-                if self.focus.is_none() && self.orientation == Orientation::Horizontal =>
-            {
-                self.selected_widget = match self.selected_widget {
-                    Widgets::Asm => Widgets::Stderr,
-                    Widgets::Stdout => Widgets::Asm,
-                    Widgets::Stderr => Widgets::Stdout,
-                };
+            (None, KeyCode::Tab) => {
+                (0..4).for_each(|_| editor.insert(' '));
                 true
             }
-            (KeyCode::Enter, _) => {
-                match self.focus {
-                    None => {
-                        self.focus = Some(self.selected_widget);
-                    }
-                    Some(_) => {
-                        self.focus = None;
-                    }
-                }
+            (None, KeyCode::Backspace) => {
+                editor.backspace();
+                true
+            }
+            (None, KeyCode::Delete) => {
+                editor.delete();
                 true
             }
-            _ => false,
+            _ => return None,
         };
-        if update_ui {
-            self.draw(terminal)?;
+        let row = editor.cursor().0;
+        self.set_cursor(row);
+        if !edited {
+            return None;
         }
-        Ok(())
+        let text = self.tab().editor.text();
+        self.tab_mut().source = Some(text.clone());
+        Some(Command::Edit(self.current, text))
     }
 
-    pub fn draw<B: Backend>(&self, terminal: &mut Terminal<B>) -> Result<(), std::io::Error> {
-        let mut asm_text = Text::default();
-        let mut stdout_text = Text::default();
-        let mut stderr_text = Text::default();
+    /// Moves the cursor of the editor pane along its line.
+    fn move_in_editor(&mut self, motion: Motion) {
+        let row = self.tab().widget_config[Widgets::Editor as usize].cursor;
+        let editor = &mut self.tab_mut().editor;
+        editor.set_cursor(row, editor.cursor().1);
+        editor.move_cursor(motion);
+    }
+
+    /// Scrolls the searched widget to the next match in the direction of the search, or in the
+    /// opposite one when `reverse` is set.
+    fn next_match(&mut self, reverse: bool) {
+        let (widget, search) = match &self.tab().search {
+            Some(search) => search,
+            None => return,
+        };
+        let (_, text) = self.widget_text(*widget);
+        let matches = search.matches(&text);
+        let offset = self.tab().widget_config[*widget as usize].cursor;
+        let direction = if reverse {
+            search.direction.reversed()
+        } else {
+            search.direction
+        };
+        let current = search.next(&matches, offset, direction);
+        self.scroll_to_match(&matches, current);
+    }
+
+    fn jump_to_match(&mut self, offset: usize, direction: Direction, skip_current: bool) {
+        let (widget, search) = match &self.tab().search {
+            Some(search) => search,
+            None => return,
+        };
+        let (_, text) = self.widget_text(*widget);
+        let matches = search.matches(&text);
+        let current = search.find(&matches, offset, direction, skip_current);
+        self.scroll_to_match(&matches, current);
+    }
+
+    fn scroll_to_match(&mut self, matches: &[usize], current: Option<usize>) {
+        if let Some((widget, search)) = &mut self.tab_mut().search {
+            search.current = current;
+            if let Some(current) = current {
+                let widget = *widget;
+                self.scroll_to(widget, matches[current]);
+            }
+        }
+    }
+
+    fn selected_config(&mut self) -> &mut WidgetConfig {
+        let widget = self.selected_widget;
+        &mut self.tab_mut().widget_config[widget as usize]
+    }
+
+    /// Rows `widget` had room for inside its borders when last drawn, at least one.
+    fn height(&self, widget: Widgets) -> usize {
+        self.areas.get()[widget as usize]
+            .height
+            .saturating_sub(2)
+            .max(1) as usize
+    }
+
+    /// The visible widget drawn next to the selected one on `side`.
+    fn neighbor(&self, side: Side) -> Option<Widgets> {
+        let areas = self.areas.get();
+        let from = areas[self.selected_widget as usize];
+        if from.area() == 0 {
+            return None;
+        }
+        let drawn: Vec<(Widgets, Rect)> = self
+            .visible_widgets()
+            .into_iter()
+            .map(|widget| (widget, areas[widget as usize]))
+            .filter(|(_, area)| area.area() > 0)
+            .collect();
+        layout::neighbor(&drawn, from, side)
+    }
+
+    fn pane_visible(&self) -> impl Fn(Pane) -> bool + '_ {
+        |pane| self.is_visible(pane.into())
+    }
+
+    /// Grows the selected widget by `step`, or shrinks it when negative, at the expense of the
+    /// widgets it shares a split with.
+    fn resize_selected(&mut self, step: i32) {
+        let mut layout = self.layout.clone();
+        layout.resize(self.selected_widget.into(), step, &self.pane_visible());
+        self.layout = layout;
+    }
+
+    fn line_count(&self, widget: Widgets) -> usize {
+        self.widget_text(widget).1.lines.len()
+    }
+
+    /// Moves the cursor of the selected widget `step` lines.
+    fn move_cursor(&mut self, step: isize) {
+        let cursor = match self.selected_widget {
+            Widgets::Outline => self.tab().outline_selected,
+            widget => self.tab().widget_config[widget as usize].cursor,
+        };
+        self.set_cursor(cursor.saturating_add_signed(step));
+    }
+
+    /// Moves the cursor of the selected widget to `line`, or to its last line past the end, and
+    /// scrolls just enough to keep it in view. The outline moves its selection instead.
+    fn set_cursor(&mut self, line: usize) {
+        let widget = self.selected_widget;
+        if widget == Widgets::Outline {
+            self.select_symbol(line);
+            return;
+        }
+        let last = self.line_count(widget).saturating_sub(1);
+        let height = self.height(widget);
+        let config = &mut self.tab_mut().widget_config[widget as usize];
+        config.cursor = line.min(last);
+        if config.cursor < config.vertical_offset {
+            config.vertical_offset = config.cursor;
+        } else if config.cursor >= config.vertical_offset + height {
+            config.vertical_offset = config.cursor + 1 - height;
+        }
+    }
+
+    /// Scrolls `widget` `step` lines, moving its cursor along when it goes out of view.
+    fn scroll(&mut self, widget: Widgets, step: isize) {
+        let height = self.height(widget);
+        let max_offset = self.line_count(widget).saturating_sub(height);
+        let config = &mut self.tab_mut().widget_config[widget as usize];
+        let offset = config
+            .vertical_offset
+            .saturating_add_signed(step)
+            .min(max_offset);
+        config.vertical_offset = offset;
+        config.cursor = config.cursor.clamp(offset, offset + height - 1);
+    }
+
+    /// Moves the cursor of `widget` to `line` and scrolls it to the top, as far as the content
+    /// allows.
+    fn scroll_to(&mut self, widget: Widgets, line: usize) {
+        let max_offset = self.line_count(widget).saturating_sub(self.height(widget));
+        let config = &mut self.tab_mut().widget_config[widget as usize];
+        config.cursor = line;
+        config.vertical_offset = line.min(max_offset);
+    }
+
+    /// Copies the visual selection, or the cursor line without one, of the selected widget.
+    /// `all` copies the whole widget instead.
+    fn yank(&mut self, all: bool) -> Option<Command> {
+        let widget = self.selected_widget;
+        let (_, text) = self.widget_text(widget);
+        let config = &mut self.tab_mut().widget_config[widget as usize];
+        let lines = if all {
+            0..=text.lines.len().saturating_sub(1)
+        } else {
+            config.selection()
+        };
+        config.anchor = None;
+
+        // The spans hold the text without the escape codes that colored it
+        let yanked = text
+            .lines
+            .get(lines)?
+            .iter()
+            .map(line_text)
+            .collect::<Vec<_>>()
+            .join("\n");
+        Some(Command::Yank(yanked))
+    }
+
+    /// Opens the documentation popup for the instruction under the cursor of the ASM widget.
+    fn show_docs(&mut self) -> Option<Command> {
+        if self.selected_widget != Widgets::Asm {
+            return None;
+        }
+        let asm = &self.tab().data.as_ref()?.asm;
+        let cursor = self.tab().widget_config[Widgets::Asm as usize].cursor;
+        let opcode = match self.asm_rows(asm).get(cursor) {
+            Some(AsmRow::Line(line)) => mnemonic(&asm[*line].text),
+            _ => None,
+        };
+        let (kind, command) = match opcode {
+            Some(opcode) => (
+                PopupKind::Docs {
+                    opcode: opcode.to_owned(),
+                    docs: None,
+                },
+                Some(Command::InstructionDocs(opcode.to_owned())),
+            ),
+            None => (
+                PopupKind::Docs {
+                    opcode: String::new(),
+                    docs: Some(Err("No instruction under the cursor".to_owned())),
+                },
+                None,
+            ),
+        };
+        self.popup = Some(Popup { kind, scroll: 0 });
+        command
+    }
+
+    /// Shows or hides an optional widget. Showing it may need a new compilation to get its output.
+    fn toggle_widget(&mut self, widget: Widgets) -> Option<Command> {
+        let produce = self.produce(self.current);
+        if let Some(index) = self.enabled_widgets.iter().position(|w| *w == widget) {
+            self.enabled_widgets.remove(index);
+            if self.selected_widget == widget {
+                self.selected_widget = self
+                    .visible_widgets()
+                    .first()
+                    .copied()
+                    .unwrap_or(Widgets::Asm);
+            }
+            if self.focus == Some(widget) {
+                self.focus = None;
+            }
+            None
+        } else {
+            self.enabled_widgets.push(widget);
+            self.selected_widget = widget;
+            if self.produce(self.current) != produce {
+                // The other tabs are compiled again once shown
+                for (index, tab) in self.tabs.iter_mut().enumerate() {
+                    tab.stale = index != self.current;
+                }
+                Some(Command::Recompile(self.current))
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Shows the tab `step` tabs forward, wrapping around. Its file is compiled again if the
+    /// widgets shown changed since its last compilation.
+    fn select_next_tab(&mut self, step: isize) -> Option<Command> {
+        self.current = (self.current as isize + step).rem_euclid(self.tabs.len() as isize) as usize;
+        let tab = self.tab_mut();
+        if std::mem::take(&mut tab.stale) {
+            Some(Command::Recompile(self.current))
+        } else {
+            None
+        }
+    }
+
+    /// Moves the selection `step` visible widgets forward, wrapping around.
+    fn select_next_widget(&mut self, step: isize) {
+        let visible = self.visible_widgets();
+        if visible.is_empty() {
+            return;
+        }
+        let next = match visible.iter().position(|w| *w == self.selected_widget) {
+            Some(index) => (index as isize + step).rem_euclid(visible.len() as isize) as usize,
+            None => 0,
+        };
+        self.selected_widget = visible[next];
+    }
+
+    /// Moves to another pass in the GCC dump or optimization pipeline widgets, or to another
+    /// function in the control flow graph widget.
+    fn select_next_entry(&mut self, step: isize) -> Option<Command> {
+        match self.selected_widget {
+            Widgets::GccDump => {
+                let passes = &self.tab().data.as_ref()?.gcc_dump.as_ref()?.passes;
+                if passes.is_empty() {
+                    return None;
+                }
+                let next = match self
+                    .tab()
+                    .gcc_dump_pass
+                    .as_ref()
+                    .and_then(|current| passes.iter().position(|pass| pass == current))
+                {
+                    Some(index) => (index as isize + step).clamp(0, passes.len() as isize - 1),
+                    None => 0,
+                };
+                self.tab_mut().gcc_dump_pass = Some(passes[next as usize].clone());
+                // The server only returns the dump of the selected pass
+                Some(Command::Recompile(self.current))
+            }
+            Widgets::OptPipeline => {
+                let passes = self.changed_passes().len();
+                let tab = self.tab_mut();
+                tab.opt_pipeline_pass = (tab.opt_pipeline_pass as isize + step)
+                    .clamp(0, passes.saturating_sub(1) as isize)
+                    as usize;
+                tab.widget_config[Widgets::OptPipeline as usize] = WidgetConfig::default();
+                None
+            }
+            Widgets::Cfg => {
+                let functions = cfg::build(&self.tab().data.as_ref()?.asm);
+                if functions.is_empty() {
+                    return None;
+                }
+                let next = (self.cfg_function_index(&functions) as isize + step)
+                    .clamp(0, functions.len() as isize - 1);
+                let tab = self.tab_mut();
+                tab.cfg_function = Some(functions[next as usize].name.clone());
+                tab.widget_config[Widgets::Cfg as usize] = WidgetConfig::default();
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn symbols(&self) -> Vec<outline::Symbol> {
+        self.tab()
+            .data
+            .as_ref()
+            .map(|data| outline::parse(&data.asm))
+            .unwrap_or_default()
+    }
+
+    /// Selects the function at `index` in the outline, or the last one past the end, and scrolls
+    /// the ASM widget to it.
+    fn select_symbol(&mut self, index: usize) {
+        let symbols = self.symbols();
+        if symbols.is_empty() {
+            return;
+        }
+        self.tab_mut().outline_selected = index.min(symbols.len() - 1);
+        // Keep some context above the selection
+        let tab = self.tab_mut();
+        tab.widget_config[Widgets::Outline as usize].vertical_offset =
+            tab.outline_selected.saturating_sub(3);
+        self.jump_to_symbol(&symbols[self.tab().outline_selected]);
+    }
+
+    fn jump_to_symbol(&mut self, symbol: &outline::Symbol) {
+        let asm = match &self.tab().data {
+            Some(data) => &data.asm,
+            None => return,
+        };
+        let row = self
+            .asm_rows(asm)
+            .iter()
+            .position(|row| matches!(row, AsmRow::Line(line) if *line == symbol.line))
+            .unwrap_or(0);
+        self.scroll_to(Widgets::Asm, row);
+    }
+
+    /// Folds or unfolds the function selected in the outline or, from the ASM widget, the one
+    /// under the cursor.
+    fn toggle_fold(&mut self) {
+        let symbols = self.symbols();
+        let symbol =
+            match self.selected_widget {
+                Widgets::Outline => symbols.get(self.tab().outline_selected),
+                Widgets::Asm => {
+                    let asm = match &self.tab().data {
+                        Some(data) => &data.asm,
+                        None => return,
+                    };
+                    let cursor = self.tab().widget_config[Widgets::Asm as usize].cursor;
+                    let line =
+                        self.asm_rows(asm).iter().take(cursor + 1).rev().find_map(
+                            |row| match row {
+                                AsmRow::Line(line) => Some(*line),
+                                AsmRow::Folded(_) => None,
+                            },
+                        );
+                    line.and_then(|line| symbols.iter().rev().find(|symbol| symbol.line <= line))
+                }
+                _ => None,
+            };
+        if let Some(symbol) = symbol {
+            let folded = &mut self.tab_mut().folded;
+            if !folded.remove(&symbol.name) {
+                folded.insert(symbol.name.clone());
+            }
+            self.jump_to_symbol(symbol);
+        }
+    }
 
-        if let Some(compilation) = &self.data {
-            for asm in &compilation.asm {
-                asm_text.extend(ansi_to_text(asm.text.bytes()).unwrap());
+    /// Folds every function, or unfolds them all if any is folded.
+    fn toggle_fold_all(&mut self) {
+        if self.tab().folded.is_empty() {
+            self.tab_mut().folded = self
+                .symbols()
+                .into_iter()
+                .map(|symbol| symbol.name)
+                .collect();
+        } else {
+            self.tab_mut().folded.clear();
+        }
+        self.tab_mut().widget_config[Widgets::Asm as usize] = WidgetConfig::default();
+    }
+
+    /// Rows of the ASM widget, replacing the bodies of folded functions with a placeholder.
+    fn asm_rows(&self, asm: &[AsmOutput]) -> Vec<AsmRow> {
+        let mut rows = vec![];
+        let mut line = 0;
+        for symbol in outline::parse(asm) {
+            if !self.tab().folded.contains(&symbol.name) {
+                continue;
             }
+            rows.extend((line..=symbol.line).map(AsmRow::Line));
+            rows.push(AsmRow::Folded(symbol.instructions));
+            line = symbol.end;
+        }
+        rows.extend((line..asm.len()).map(AsmRow::Line));
+        rows
+    }
+
+    /// Line of the source the instruction under the ASM cursor comes from. Labels and other
+    /// lines without one take the line of the closest instruction above them.
+    fn asm_cursor_source_line(&self) -> Option<i64> {
+        let asm = &self.tab().data.as_ref()?.asm;
+        let cursor = self.tab().widget_config[Widgets::Asm as usize].cursor;
+        self.asm_rows(asm)
+            .iter()
+            .take(cursor + 1)
+            .rev()
+            .find_map(|row| match row {
+                // Lines from other files, like headers, are of no use here
+                AsmRow::Line(line) => match &asm[*line].source {
+                    Some((None, line)) => Some(*line),
+                    _ => None,
+                },
+                AsmRow::Folded(_) => None,
+            })
+    }
 
-            for stdout in &compilation.stdout {
-                stdout_text.extend(ansi_to_text(stdout.text.bytes()).unwrap());
+    fn outline_text(&self) -> Text<'static> {
+        let mut text = Text::default();
+        for (index, symbol) in self.symbols().iter().enumerate() {
+            let marker = if self.tab().folded.contains(&symbol.name) {
+                "▸"
+            } else {
+                "▾"
+            };
+            let mut style = Style::default();
+            if index == self.tab().outline_selected {
+                style = style.add_modifier(Modifier::REVERSED);
             }
+            text.extend(Text::from(Spans::from(vec![
+                Span::styled(format!("{} {}", marker, symbol.name), style),
+                Span::styled(
+                    format!(" {}", symbol.instructions),
+                    Style::default().fg(self.theme.dim),
+                ),
+            ])));
+        }
+        text
+    }
+
+    fn cfg_function_index(&self, functions: &[cfg::Function]) -> usize {
+        self.tab()
+            .cfg_function
+            .as_ref()
+            .and_then(|name| functions.iter().position(|function| &function.name == name))
+            .unwrap_or(0)
+    }
+
+    fn cfg_text(&self, compilation: &CompilationResult) -> (String, Text<'static>) {
+        let functions = cfg::build(&compilation.asm);
+        if functions.is_empty() {
+            return ("CFG".to_owned(), Text::raw("<No functions>"));
+        }
+        let index = self.cfg_function_index(&functions);
+        let function = &functions[index];
+        (
+            format!("CFG: {} ({}/{})", function.name, index + 1, functions.len()),
+            Text::from(cfg::render(function).join("\n")),
+        )
+    }
 
-            for stderr in &compilation.stderr {
-                stderr_text.extend(ansi_to_text(stderr.text.bytes()).unwrap());
+    /// Passes of the optimization pipeline that changed the IR, with the function they ran on.
+    fn changed_passes(&self) -> Vec<(&str, &OptPipelinePass)> {
+        self.tab()
+            .data
+            .iter()
+            .flat_map(|data| data.opt_pipeline.iter().flatten())
+            .flat_map(|function| {
+                function
+                    .passes
+                    .iter()
+                    .filter(|pass| pass.changed)
+                    .map(move |pass| (function.name.as_str(), pass))
+            })
+            .collect()
+    }
+
+    fn is_visible(&self, widget: Widgets) -> bool {
+        if !self.enabled_widgets.contains(&widget) {
+            return false;
+        }
+        if widget.is_optional() {
+            return true;
+        }
+        let data = match &self.tab().data {
+            Some(data) => data,
+            None => return false,
+        };
+        let execution = data.execution.as_ref();
+        match widget {
+            Widgets::Asm => !data.asm.is_empty(),
+            Widgets::Stdout => {
+                !data.stdout.is_empty() || execution.is_some_and(|e| !e.stdout.is_empty())
+            }
+            Widgets::Stderr => {
+                !data.stderr.is_empty() || execution.is_some_and(|e| !e.stderr.is_empty())
             }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Visible widgets: the outline, then the rest in the order of the layout.
+    fn visible_widgets(&self) -> Vec<Widgets> {
+        std::iter::once(Widgets::Outline)
+            .chain(self.layout.panes().into_iter().map(Widgets::from))
+            .filter(|widget| self.is_visible(*widget))
+            .collect()
+    }
+
+    fn asm_text(&self, compilation: &CompilationResult) -> Text<'static> {
+        let mut asm_text = Text::default();
+        // Addresses and opcodes are only available when disassembling binaries
+        let address_width = compilation
+            .asm
+            .iter()
+            .filter_map(|asm| asm.address)
+            .map(|address| format!("{:x}", address).len())
+            .max()
+            .filter(|_| self.show_addresses);
+        let opcodes_width = compilation
+            .asm
+            .iter()
+            .map(|asm| asm.opcodes.join(" ").len())
+            .max()
+            .filter(|width| *width > 0 && self.show_opcodes);
 
-            if let Some(execution_result) = &compilation.execution {
-                for stdout in &execution_result.stdout {
-                    stdout_text.extend(ansi_to_text(stdout.text.bytes()).unwrap());
+        for row in self.asm_rows(&compilation.asm) {
+            let asm = match row {
+                AsmRow::Line(line) => &compilation.asm[line],
+                AsmRow::Folded(instructions) => {
+                    asm_text.extend(Text::styled(
+                        format!("        ... {} instructions", instructions),
+                        Style::default().fg(self.theme.dim),
+                    ));
+                    continue;
                 }
+            };
+            let mut text = self.highlight_asm(&asm.text);
+            let mut columns = vec![];
+            if let Some(width) = address_width {
+                let address = asm
+                    .address
+                    .map(|address| format!("{:>width$x}: ", address, width = width))
+                    .unwrap_or_else(|| " ".repeat(width + 2));
+                columns.push(Span::styled(address, Style::default().fg(self.theme.dim)));
+            }
+            if let Some(width) = opcodes_width {
+                columns.push(Span::styled(
+                    format!("{:<width$} ", asm.opcodes.join(" "), width = width),
+                    Style::default().fg(self.theme.opcodes),
+                ));
+            }
+            if let Some(line) = text.lines.first_mut() {
+                line.0.splice(0..0, columns);
+            }
+            asm_text.extend(text);
+        }
+        asm_text
+    }
+
+    /// Colors the tokens of an assembly line, unless the compiler already colored it.
+    fn highlight_asm(&self, line: &str) -> Text<'static> {
+        if line.contains('\x1b') {
+            return colored_text(line);
+        }
+        let spans: Vec<Span> = highlight::tokenize(line)
+            .into_iter()
+            .map(|(kind, token)| match self.theme.token(kind) {
+                Some(color) => Span::styled(token.to_owned(), Style::default().fg(color)),
+                None => Span::raw(token.to_owned()),
+            })
+            .collect();
+        Text::from(Spans::from(spans))
+    }
 
-                for stderr in &execution_result.stderr {
-                    stderr_text.extend(ansi_to_text(stderr.text.bytes()).unwrap());
+    fn opt_pipeline_text(&self) -> (String, Text<'static>) {
+        let passes = self.changed_passes();
+        let (function, pass) =
+            match passes.get(self.tab().opt_pipeline_pass.min(passes.len().max(1) - 1)) {
+                Some(pass) => *pass,
+                None => {
+                    return (
+                        "Opt pipeline".to_owned(),
+                        Text::raw("<No pass changed the IR>"),
+                    )
                 }
+            };
+
+        // Highlight the lines the pass introduced
+        let before: HashSet<&str> = pass.before.iter().map(|line| line.as_str()).collect();
+        let mut text = Text::default();
+        for line in &pass.after {
+            if before.contains(line.as_str()) {
+                text.extend(Text::raw(line.clone()));
+            } else {
+                text.extend(Text::styled(
+                    line.clone(),
+                    Style::default().fg(self.theme.added),
+                ));
             }
         }
+        (
+            format!(
+                "Opt pipeline: {} on {} ({}/{})",
+                pass.name,
+                function,
+                self.tab().opt_pipeline_pass.min(passes.len() - 1) + 1,
+                passes.len()
+            ),
+            text,
+        )
+    }
 
-        let mut num_blocks = 0;
-        let mut show_asm = false;
-        if !asm_text.lines.is_empty() {
-            num_blocks += 1;
-            show_asm = true;
+    /// Optimization remarks that pass the filter, sorted by location.
+    fn remarks(&self) -> Vec<&OptRemark> {
+        let mut remarks: Vec<_> = self
+            .tab()
+            .data
+            .iter()
+            .flat_map(|data| data.opt_remarks.iter().flatten())
+            .filter(|remark| self.remark_filter.is_none_or(|kind| remark.kind == kind))
+            .collect();
+        remarks.sort_by_key(|remark| remark.location);
+        remarks
+    }
+
+    fn remark_filter_title(&self, title: &str) -> String {
+        match self.remark_filter {
+            Some(kind) => format!("{} ({:?})", title, kind),
+            None => title.to_owned(),
         }
-        let asm_block = Self::draw_paragraph_block(
-            "ASM",
-            asm_text,
-            self.selected_widget == Widgets::Asm,
-            &self.widget_config[0],
-        );
-        let mut show_stdout = false;
-        if !stdout_text.lines.is_empty() {
-            num_blocks += 1;
-            show_stdout = true;
-        }
-        let stdout_block = Self::draw_paragraph_block(
-            "Stdout",
-            stdout_text,
-            self.selected_widget == Widgets::Stdout,
-            &self.widget_config[1],
-        );
-        let mut show_stderr = false;
-        if !stderr_text.lines.is_empty() {
-            num_blocks += 1;
-            show_stderr = true;
-        }
-        let stderr_block = Self::draw_paragraph_block(
-            "Stderr",
-            stderr_text,
-            self.selected_widget == Widgets::Stderr,
-            &self.widget_config[2],
-        );
+    }
+
+    fn remark_style(&self, kind: RemarkKind) -> Style {
+        Style::default().fg(match kind {
+            RemarkKind::Passed => self.theme.added,
+            RemarkKind::Missed => self.theme.removed,
+            RemarkKind::Analysis => self.theme.warning,
+        })
+    }
+
+    /// Source with line numbers and the optimization remarks below the line they refer to.
+    fn source_text(&self) -> Text<'static> {
+        let source = match &self.tab().source {
+            Some(source) => source,
+            None => return Text::default(),
+        };
+        let remarks = self.remarks();
+        let width = source.lines().count().to_string().len();
+
+        let mut text = Text::default();
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index as i64 + 1;
+            text.extend(Text::from(Spans::from(vec![
+                Span::styled(
+                    format!("{:>width$} ", line_number, width = width),
+                    Style::default().fg(self.theme.dim),
+                ),
+                Span::raw(line.replace('\t', "    ")),
+            ])));
+            for remark in remarks
+                .iter()
+                .filter(|remark| remark.location.map(|(line, _)| line) == Some(line_number))
+            {
+                let column = remark.location.map_or(0, |(_, column)| column.max(1) - 1);
+                let pass = if remark.pass.is_empty() {
+                    String::new()
+                } else {
+                    format!("{}: ", remark.pass)
+                };
+                text.extend(Text::styled(
+                    format!(
+                        "{:width$} {:column$}^ {}{}",
+                        "",
+                        "",
+                        pass,
+                        remark.message,
+                        width = width,
+                        column = column as usize
+                    ),
+                    self.remark_style(remark.kind),
+                ));
+            }
+        }
+        text
+    }
 
-        if num_blocks == 0 {
-            num_blocks = 1;
+    /// Lines of the editor pane, with the character under the cursor reversed while it is
+    /// selected.
+    fn editor_text(&self) -> Text<'static> {
+        let editor = &self.tab().editor;
+        let (row, column) = editor.cursor();
+        let width = editor.lines().len().to_string().len();
+        let expand = |text: String| Span::raw(text.replace('\t', "    "));
+
+        let mut text = Text::default();
+        for (index, line) in editor.lines().iter().enumerate() {
+            let mut spans = vec![Span::styled(
+                format!("{:>width$} ", index + 1, width = width),
+                Style::default().fg(self.theme.dim),
+            )];
+            if index == row && self.selected_widget == Widgets::Editor {
+                let mut chars = line.chars();
+                spans.push(expand(chars.by_ref().take(column).collect()));
+                let cursor = chars.next().map_or(" ".to_owned(), String::from);
+                spans.push(Span::styled(
+                    cursor.replace('\t', " "),
+                    Style::default().add_modifier(Modifier::REVERSED),
+                ));
+                spans.push(expand(chars.collect()));
+            } else {
+                spans.push(expand(line.clone()));
+            }
+            text.extend(Text::from(Spans::from(spans)));
         }
-        let percentage = 100 / num_blocks;
-        let mut constraints = vec![];
-        for _ in 0..num_blocks {
-            constraints.push(tui::layout::Constraint::Percentage(percentage));
+        text
+    }
+
+    fn remarks_text(&self) -> Text<'static> {
+        let mut text = Text::default();
+        for remark in self.remarks() {
+            let location = remark
+                .location
+                .map(|(line, column)| format!("{}:{} ", line, column))
+                .unwrap_or_default();
+            let pass = if remark.pass.is_empty() {
+                String::new()
+            } else {
+                format!(" {}", remark.pass)
+            };
+            text.extend(Text::from(Spans::from(vec![
+                Span::styled(location, Style::default().fg(self.theme.dim)),
+                Span::styled(
+                    format!("{:?}{}: ", remark.kind, pass),
+                    self.remark_style(remark.kind),
+                ),
+                Span::raw(remark.message.clone()),
+            ])));
         }
+        text
+    }
 
-        terminal
-            .draw(|f| match self.focus {
-                Some(Widgets::Asm) => {
-                    f.render_widget(asm_block, f.size());
+    /// Title and contents of `widget`.
+    fn widget_text(&self, widget: Widgets) -> (String, Text<'static>) {
+        if widget == Widgets::Source {
+            return (self.remark_filter_title("Source"), self.source_text());
+        }
+        if widget == Widgets::Editor {
+            let title = if self.inserting {
+                "Editor [insert]"
+            } else {
+                "Editor"
+            };
+            return (title.to_owned(), self.editor_text());
+        }
+        let lines_text = |lines: &[AsmOutput]| {
+            let mut text = Text::default();
+            for line in lines {
+                text.extend(colored_text(&line.text));
+            }
+            text
+        };
+        let stream_text = |streams: &[&[StreamOutput]]| {
+            let mut text = Text::default();
+            for stream in streams.iter().flat_map(|stream| stream.iter()) {
+                text.extend(colored_text(&stream.text));
+            }
+            text
+        };
+        let unavailable = || Text::raw("<Not produced by this compiler>");
+
+        let compilation = match &self.tab().data {
+            Some(compilation) => compilation,
+            None => return (String::new(), Text::default()),
+        };
+        let execution = compilation.execution.as_ref();
+        match widget {
+            Widgets::Asm => ("ASM".to_owned(), self.asm_text(compilation)),
+            Widgets::Stdout => (
+                "Stdout".to_owned(),
+                stream_text(&[
+                    &compilation.stdout,
+                    execution.map_or(&[], |e| e.stdout.as_slice()),
+                ]),
+            ),
+            Widgets::Stderr => (
+                "Stderr".to_owned(),
+                stream_text(&[
+                    &compilation.stderr,
+                    execution.map_or(&[], |e| e.stderr.as_slice()),
+                ]),
+            ),
+            Widgets::Ir => (
+                "LLVM IR".to_owned(),
+                compilation
+                    .ir
+                    .as_deref()
+                    .map_or_else(unavailable, lines_text),
+            ),
+            Widgets::Ast => (
+                "AST".to_owned(),
+                compilation
+                    .ast
+                    .as_deref()
+                    .map_or_else(unavailable, lines_text),
+            ),
+            Widgets::GccDump => match &compilation.gcc_dump {
+                Some(dump) => (
+                    match &dump.selected_pass {
+                        Some(pass) => format!("GCC dump: {}", pass.name),
+                        None => "GCC dump".to_owned(),
+                    },
+                    Text::raw(dump.output.clone()),
+                ),
+                None => ("GCC dump".to_owned(), unavailable()),
+            },
+            Widgets::OptPipeline if compilation.opt_pipeline.is_none() => {
+                ("Opt pipeline".to_owned(), unavailable())
+            }
+            Widgets::OptPipeline => self.opt_pipeline_text(),
+            Widgets::Remarks if compilation.opt_remarks.is_none() => {
+                ("Remarks".to_owned(), unavailable())
+            }
+            Widgets::Remarks => (self.remark_filter_title("Remarks"), self.remarks_text()),
+            Widgets::Cfg => self.cfg_text(compilation),
+            Widgets::Outline => ("Outline".to_owned(), self.outline_text()),
+            Widgets::Source | Widgets::Editor => unreachable!(),
+        }
+    }
+
+    pub fn draw<B: Backend>(&self, terminal: &mut Terminal<B>) -> Result<(), std::io::Error> {
+        let mut blocks: Vec<(Widgets, Paragraph)> = self
+            .visible_widgets()
+            .into_iter()
+            .map(|widget| {
+                let (mut title, mut text) = self.widget_text(widget);
+                if widget != Widgets::Outline && self.selected_widget == widget {
+                    let config = &self.tab().widget_config[widget as usize];
+                    for (index, line) in text.lines.iter_mut().enumerate() {
+                        let background = if index == config.cursor {
+                            self.theme.cursor
+                        } else if config.selection().contains(&index) {
+                            self.theme.selection
+                        } else {
+                            continue;
+                        };
+                        line.0
+                            .iter_mut()
+                            .for_each(|span| span.style = span.style.bg(background));
+                    }
                 }
-                Some(Widgets::Stdout) => {
-                    f.render_widget(stdout_block, f.size());
+                if let Some((searched, search)) = &self.tab().search {
+                    if *searched == widget {
+                        let matches = search.matches(&text).len();
+                        title = match search.current {
+                            Some(current) => format!("{} [{}/{}]", title, current + 1, matches),
+                            None => format!("{} [{} matches]", title, matches),
+                        };
+                        text = search.highlight(text, &self.theme);
+                    }
                 }
-                Some(Widgets::Stderr) => {
-                    f.render_widget(stderr_block, f.size());
+                let block = self.draw_paragraph_block(
+                    title,
+                    text,
+                    self.selected_widget == widget,
+                    &self.tab().widget_config[widget as usize],
+                );
+                (widget, block)
+            })
+            .collect();
+
+        // The outline is a sidebar on the left, whatever the orientation
+        let outline = match blocks.first() {
+            Some((Widgets::Outline, _)) if self.focus.is_none() => Some(blocks.remove(0).1),
+            _ => None,
+        };
+        let outline_width = self
+            .symbols()
+            .iter()
+            .map(|symbol| symbol.name.chars().count() as u16 + 8)
+            .max()
+            .unwrap_or(20)
+            .clamp(20, 40);
+
+        let error_block = self
+            .tab()
+            .error
+            .as_ref()
+            .map(|error| self.draw_error_block(error));
+
+        if let Some(focus) = self.focus {
+            blocks.retain(|(widget, _)| *widget == focus);
+        }
+
+        let status = self
+            .status
+            .as_ref()
+            .map(|status| Paragraph::new(Text::raw(status.clone())));
+        let prompt = self
+            .prompt
+            .as_ref()
+            .map(|prompt| {
+                let mut spans = vec![Span::raw(format!(
+                    "{}{}",
+                    prompt.direction.prefix(),
+                    prompt.input
+                ))];
+                if let Some(error) = &prompt.error {
+                    spans.push(Span::styled(
+                        format!("  {}", error),
+                        Style::default().fg(self.theme.error),
+                    ));
                 }
-                None => {
+                Paragraph::new(Spans::from(spans))
+            })
+            .or(status);
+
+        let popup = self.popup.as_ref().map(|popup| self.draw_popup(popup));
+        let tab_bar = (self.tabs.len() > 1).then(|| self.draw_tab_bar());
+
+        let mut areas = [Rect::default(); Widgets::COUNT];
+        terminal
+            .draw(|f| {
+                let mut area = f.size();
+                let screen = area;
+                if let Some(tab_bar) = tab_bar {
                     let parts = tui::layout::Layout::default()
-                        .direction(if self.orientation == Orientation::Vertical {
-                            tui::layout::Direction::Vertical
-                        } else {
-                            tui::layout::Direction::Horizontal
-                        })
-                        .constraints(constraints)
-                        .split(f.size());
-
-                    let mut used_constraints = 0;
-                    if show_asm {
-                        f.render_widget(asm_block, parts[used_constraints]);
-                        used_constraints += 1;
-                    }
-                    if show_stdout {
-                        f.render_widget(stdout_block, parts[used_constraints]);
-                        used_constraints += 1;
+                        .direction(tui::layout::Direction::Vertical)
+                        .constraints([
+                            tui::layout::Constraint::Length(1),
+                            tui::layout::Constraint::Min(0),
+                        ])
+                        .split(area);
+                    f.render_widget(tab_bar, parts[0]);
+                    area = parts[1];
+                }
+                if let Some(prompt) = prompt {
+                    let parts = tui::layout::Layout::default()
+                        .direction(tui::layout::Direction::Vertical)
+                        .constraints([
+                            tui::layout::Constraint::Min(0),
+                            tui::layout::Constraint::Length(1),
+                        ])
+                        .split(area);
+                    f.render_widget(prompt, parts[1]);
+                    area = parts[0];
+                }
+                if let Some((error_block, error_lines)) = error_block {
+                    // Leave at least half of the screen for the panes
+                    let height = (error_lines + 2).min(area.height / 2);
+                    let parts = tui::layout::Layout::default()
+                        .direction(tui::layout::Direction::Vertical)
+                        .constraints([
+                            tui::layout::Constraint::Length(height),
+                            tui::layout::Constraint::Min(0),
+                        ])
+                        .split(area);
+                    f.render_widget(error_block, parts[0]);
+                    area = parts[1];
+                }
+
+                match self.focus {
+                    Some(_) => {
+                        if let Some((widget, block)) = blocks.pop() {
+                            areas[widget as usize] = area;
+                            f.render_widget(block, area);
+                        }
                     }
-                    if show_stderr {
-                        f.render_widget(stderr_block, parts[used_constraints]);
+                    None => {
+                        if let Some(outline) = outline {
+                            let parts = tui::layout::Layout::default()
+                                .direction(tui::layout::Direction::Horizontal)
+                                .constraints([
+                                    tui::layout::Constraint::Length(outline_width),
+                                    tui::layout::Constraint::Min(0),
+                                ])
+                                .split(area);
+                            areas[Widgets::Outline as usize] = parts[0];
+                            f.render_widget(outline, parts[0]);
+                            area = parts[1];
+                        }
+
+                        self.layout_area.set(area);
+                        for (pane, part) in self.layout.areas(area, &self.pane_visible()) {
+                            let widget = Widgets::from(pane);
+                            if let Some(index) = blocks.iter().position(|(w, _)| *w == widget) {
+                                areas[widget as usize] = part;
+                                f.render_widget(blocks.swap_remove(index).1, part);
+                            }
+                        }
                     }
                 }
+
+                if let Some(popup) = popup {
+                    let area = Self::centered(screen, 80, 80);
+                    f.render_widget(Clear, area);
+                    f.render_widget(popup, area);
+                }
             })
             .unwrap();
+        self.areas.set(areas);
         Ok(())
     }
 
+    /// The name of each file, with the one shown highlighted and those that failed to compile in
+    /// the error color.
+    fn draw_tab_bar(&self) -> Paragraph<'static> {
+        let spans: Vec<Span> = self
+            .tabs
+            .iter()
+            .enumerate()
+            .map(|(index, tab)| {
+                let style = if tab.error.is_some() {
+                    Style::default().fg(self.theme.error)
+                } else if index == self.current {
+                    Style::default().fg(self.theme.title)
+                } else {
+                    Style::default().fg(self.theme.dim)
+                };
+                let style = if index == self.current {
+                    style.add_modifier(Modifier::BOLD | Modifier::REVERSED)
+                } else {
+                    style
+                };
+                Span::styled(format!(" {} ", tab.name), style)
+            })
+            .collect();
+        Paragraph::new(Spans::from(spans))
+    }
+
+    /// `width` and `height` percent of `area`, in its center.
+    fn centered(area: Rect, width: u16, height: u16) -> Rect {
+        let width = area.width * width / 100;
+        let height = area.height * height / 100;
+        Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        )
+    }
+
+    fn draw_popup(&self, popup: &Popup) -> Paragraph<'static> {
+        let (title, text) = match &popup.kind {
+            PopupKind::Docs { opcode, docs } => (opcode.clone(), self.docs_text(docs.as_ref())),
+            PopupKind::Help => ("Help".to_owned(), self.help_text()),
+        };
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Double)
+            .border_style(Style::default().fg(self.theme.selected_border))
+            .title(Span::styled(
+                title,
+                Style::default()
+                    .fg(self.theme.title)
+                    .add_modifier(Modifier::BOLD),
+            ));
+        Paragraph::new(text)
+            .block(block)
+            .wrap(Wrap { trim: false })
+            .scroll((popup.scroll, 0))
+    }
+
+    /// Every action with the keys bound to it. Actions that do nothing in the current state are
+    /// dimmed, with the reason.
+    fn help_text(&self) -> Text<'static> {
+        let bindings: Vec<(Action, String)> = Action::ALL
+            .iter()
+            .map(|action| (*action, self.keymap.sequences(*action).join(", ")))
+            .collect();
+        let width = bindings
+            .iter()
+            .map(|(_, keys)| keys.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        let mut text = Text::default();
+        for (action, keys) in bindings {
+            let keys = if keys.is_empty() {
+                "(unbound)".to_owned()
+            } else {
+                keys
+            };
+            let line = match self.unavailable(action) {
+                None => Spans::from(vec![
+                    Span::styled(
+                        format!("{:<width$}  ", keys, width = width),
+                        Style::default().fg(self.theme.opcodes),
+                    ),
+                    Span::raw(action.description()),
+                ]),
+                Some(reason) => Spans::from(Span::styled(
+                    format!(
+                        "{:<width$}  {} ({})",
+                        keys,
+                        action.description(),
+                        reason,
+                        width = width
+                    ),
+                    Style::default().fg(self.theme.dim),
+                )),
+            };
+            text.extend(Text::from(line));
+        }
+        text
+    }
+
+    fn docs_text(&self, docs: Option<&Result<InstructionDocs, String>>) -> Text<'static> {
+        match docs {
+            None => Text::styled("Loading...", Style::default().fg(self.theme.dim)),
+            Some(Err(error)) => Text::styled(error.clone(), Style::default().fg(self.theme.error)),
+            Some(Ok(docs)) => {
+                let mut text = Text::styled(
+                    docs.tooltip.clone(),
+                    Style::default().add_modifier(Modifier::BOLD),
+                );
+                if !docs.html.is_empty() {
+                    text.extend(Text::raw(""));
+                    text.extend(Text::raw(docs.text()));
+                }
+                if let Some(url) = &docs.url {
+                    text.extend(Text::raw(""));
+                    text.extend(Text::styled(
+                        url.clone(),
+                        Style::default().fg(self.theme.dim),
+                    ));
+                }
+                text
+            }
+        }
+    }
+
+    fn draw_error_block<'a>(&self, error: &'a str) -> (Paragraph<'a>, u16) {
+        let text = Text::styled(error, Style::default().fg(self.theme.error));
+        let lines = text.lines.len() as u16;
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(self.theme.error))
+            .title(Span::styled(
+                "Error",
+                Style::default()
+                    .fg(self.theme.error)
+                    .add_modifier(Modifier::BOLD),
+            ));
+
+        (
+            Paragraph::new(text).block(block).wrap(Wrap { trim: false }),
+            lines,
+        )
+    }
+
     fn draw_paragraph_block<'a>(
-        title: &'a str,
-        text: Text<'a>,
+        &self,
+        title: String,
+        mut text: Text<'a>,
         selected: bool,
         config: &WidgetConfig,
     ) -> Paragraph<'a> {
         let block = Block::default()
             .borders(Borders::ALL)
+            .border_style(Style::default().fg(if selected {
+                self.theme.selected_border
+            } else {
+                self.theme.border
+            }))
             .title(Span::styled(
                 title,
                 Style::default()
-                    .fg(Color::Magenta)
+                    .fg(self.theme.title)
                     .add_modifier(Modifier::BOLD),
             ))
             .border_type(if selected {
@@ -303,9 +2014,219 @@ impl Ui {
                 BorderType::Plain
             });
 
+        // No wrapping, the cursor, selections and scrolling all count lines of text as rows.
+        // Lines above the view are dropped rather than scrolled, Paragraph only scrolls u16 rows
+        text.lines
+            .drain(..config.vertical_offset.min(text.lines.len()));
         Paragraph::new(text)
             .block(block)
-            .wrap(Wrap { trim: false })
-            .scroll((config.vertical_offset, config.horizontal_offset))
+            .scroll((0, config.horizontal_offset))
+    }
+}
+
+/// Side of the selected pane `action` moves the selection to.
+fn pane_side(action: Action) -> Option<Side> {
+    match action {
+        Action::PaneLeft => Some(Side::Left),
+        Action::PaneRight => Some(Side::Right),
+        Action::PaneUp => Some(Side::Up),
+        Action::PaneDown => Some(Side::Down),
+        _ => None,
+    }
+}
+
+/// Turns the ANSI escape sequences compilers color their output with into styles, or keeps the
+/// text as is when they cannot be parsed.
+fn colored_text(text: &str) -> Text<'static> {
+    ansi_to_text(text.bytes()).unwrap_or_else(|e| {
+        ::log::debug!("Unable to parse escape sequences in {:?}: {:?}", text, e);
+        Text::raw(text.to_owned())
+    })
+}
+
+/// Mnemonic of an assembly line, or `None` for labels, directives and blank lines.
+fn mnemonic(text: &str) -> Option<&str> {
+    let word = text.split_whitespace().next()?;
+    let is_mnemonic = !word.starts_with('.')
+        && word.chars().next()?.is_ascii_alphabetic()
+        && word.chars().all(|c| c.is_ascii_alphanumeric() || c == '.');
+    is_mnemonic.then_some(word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::theme::Preset;
+
+    use tui::backend::TestBackend;
+
+    use std::collections::HashMap;
+
+    fn ui() -> Ui {
+        Ui::new(
+            vec!["example.cpp".to_owned()],
+            Layout::default(),
+            Keymap::new(&HashMap::new()).unwrap(),
+            Theme::preset(Preset::Dark),
+        )
+    }
+
+    /// Presses each key, returning the last command.
+    fn press(
+        ui: &mut Ui,
+        terminal: &mut Terminal<TestBackend>,
+        keys: &[KeyCode],
+    ) -> Option<Command> {
+        keys.iter().fold(None, |_, code| {
+            ui.handle_key_event(KeyEvent::new(*code, KeyModifiers::NONE), terminal)
+                .unwrap()
+        })
+    }
+
+    fn type_text(ui: &mut Ui, terminal: &mut Terminal<TestBackend>, text: &str) -> Option<Command> {
+        let keys: Vec<_> = text.chars().map(KeyCode::Char).collect();
+        press(ui, terminal, &keys)
+    }
+
+    #[test]
+    fn typing_goes_on_where_it_was_after_a_compilation() {
+        let mut ui = ui();
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        ui.set_source(0, "int f();\n".to_owned());
+
+        type_text(&mut ui, &mut terminal, "e");
+        press(&mut ui, &mut terminal, &[KeyCode::End, KeyCode::Enter]);
+        type_text(&mut ui, &mut terminal, "int g();");
+        ui.set_data(0, CompilationResult::default());
+
+        assert_eq!(
+            type_text(&mut ui, &mut terminal, "x"),
+            Some(Command::Edit(0, "int f();\nint g();x\n".to_owned()))
+        );
+    }
+
+    #[test]
+    fn points_to_the_help_until_a_key_is_pressed() {
+        let mut ui = ui();
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        assert_eq!(ui.status.as_deref(), Some("f1: help"));
+
+        press(&mut ui, &mut terminal, &[KeyCode::Char('j')]);
+        assert_eq!(ui.status, None);
+    }
+
+    #[test]
+    fn long_lines_take_a_single_row() {
+        let mut ui = ui();
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        let asm = ["x".repeat(200), "second".to_owned()]
+            .into_iter()
+            .map(|text| AsmOutput {
+                text,
+                ..Default::default()
+            })
+            .collect();
+        ui.set_data(
+            0,
+            CompilationResult {
+                asm,
+                ..Default::default()
+            },
+        );
+        ui.draw(&mut terminal).unwrap();
+
+        let buffer = terminal.backend().buffer();
+        let row_of = |needle: &str| {
+            (0..buffer.area.height).find(|y| {
+                let row: String = (0..buffer.area.width)
+                    .map(|x| buffer.get(x, *y).symbol.as_str())
+                    .collect();
+                row.contains(needle)
+            })
+        };
+        let first = row_of("xxxx").unwrap();
+        assert_eq!(row_of("second"), Some(first + 1));
+    }
+
+    #[test]
+    fn scrolls_past_u16_lines() {
+        let mut ui = ui();
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        let asm = (0..70_000)
+            .map(|line| AsmOutput {
+                text: format!("line {}", line),
+                ..Default::default()
+            })
+            .collect();
+        ui.set_data(
+            0,
+            CompilationResult {
+                asm,
+                ..Default::default()
+            },
+        );
+
+        press(&mut ui, &mut terminal, &[KeyCode::Char('G')]);
+
+        let buffer = terminal.backend().buffer();
+        let screen: String = buffer
+            .content
+            .iter()
+            .map(|cell| cell.symbol.as_str())
+            .collect();
+        assert!(screen.contains("line 69999"));
+    }
+
+    #[test]
+    fn opens_the_source_line_of_the_asm_cursor() {
+        let mut ui = ui();
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        let asm = [
+            ("square(int):", None),
+            ("        imul    edi, edi", Some((None, 2))),
+            ("        mov     eax, edi", Some((None, 3))),
+            (
+                "        call    helper",
+                Some((Some("helper.h".to_owned()), 9)),
+            ),
+            (".L2:", None),
+        ]
+        .into_iter()
+        .map(|(text, source)| AsmOutput {
+            text: text.to_owned(),
+            source,
+            ..Default::default()
+        })
+        .collect();
+        ui.set_data(
+            0,
+            CompilationResult {
+                asm,
+                ..Default::default()
+            },
+        );
+
+        let mut line_at = |cursor| {
+            ui.tab_mut().widget_config[Widgets::Asm as usize].cursor = cursor;
+            type_text(&mut ui, &mut terminal, "ge")
+        };
+        // Nothing above the first label comes from the source
+        assert_eq!(line_at(0), Some(Command::OpenInEditor(0, None)));
+        assert_eq!(line_at(2), Some(Command::OpenInEditor(0, Some(3))));
+        // Lines from headers and labels take the closest source line above them
+        assert_eq!(line_at(3), Some(Command::OpenInEditor(0, Some(3))));
+        assert_eq!(line_at(4), Some(Command::OpenInEditor(0, Some(3))));
+    }
+
+    #[test]
+    fn keeps_text_with_unparsable_escapes() {
+        let text = colored_text("ab\x1bcd");
+        assert_eq!(text.lines.len(), 1);
+        let content: String = text.lines[0]
+            .0
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect();
+        assert_eq!(content, "ab\x1bcd");
     }
 }
@@ -0,0 +1,187 @@
+//! Regex search within the text of a widget.
+
+use crate::theme::Theme;
+
+use regex::Regex;
+use tui::{
+    style::{Color, Style},
+    text::{Span, Spans, Text},
+};
+
+use std::ops::Range;
+
+use super::WidgetConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Forward,
+    Backward,
+}
+
+impl Direction {
+    pub fn prefix(self) -> char {
+        match self {
+            Direction::Forward => '/',
+            Direction::Backward => '?',
+        }
+    }
+
+    pub fn reversed(self) -> Self {
+        match self {
+            Direction::Forward => Direction::Backward,
+            Direction::Backward => Direction::Forward,
+        }
+    }
+}
+
+/// Search being typed.
+pub struct Prompt {
+    pub direction: Direction,
+    pub input: String,
+    /// Error compiling the regex typed so far
+    pub error: Option<String>,
+    /// Position in the widget when the search started, restored if the search is cancelled
+    pub origin: WidgetConfig,
+}
+
+pub struct Search {
+    pub regex: Regex,
+    pub direction: Direction,
+    /// Index of the match the widget was last scrolled to
+    pub current: Option<usize>,
+}
+
+impl Search {
+    /// Lines of every match in `text`, in order.
+    pub fn matches(&self, text: &Text) -> Vec<usize> {
+        text.lines
+            .iter()
+            .enumerate()
+            .flat_map(|(index, line)| {
+                std::iter::repeat_n(index, match_ranges(&self.regex, &line_text(line)).len())
+            })
+            .collect()
+    }
+
+    /// Finds the first match after line `offset` (or before it when going backwards), wrapping
+    /// around. `skip_current` moves past a match at `offset`, as repeating a search does.
+    pub fn find(
+        &self,
+        matches: &[usize],
+        offset: usize,
+        direction: Direction,
+        skip_current: bool,
+    ) -> Option<usize> {
+        if matches.is_empty() {
+            return None;
+        }
+        let found = match (direction, skip_current) {
+            (Direction::Forward, false) => matches.iter().position(|line| *line >= offset),
+            (Direction::Forward, true) => matches.iter().position(|line| *line > offset),
+            (Direction::Backward, false) => matches.iter().rposition(|line| *line <= offset),
+            (Direction::Backward, true) => matches.iter().rposition(|line| *line < offset),
+        };
+        found.or(match direction {
+            Direction::Forward => Some(0),
+            Direction::Backward => Some(matches.len() - 1),
+        })
+    }
+
+    /// Finds the match after the current one, or the first one past line `offset` if the widget
+    /// was scrolled away from it.
+    pub fn next(&self, matches: &[usize], offset: usize, direction: Direction) -> Option<usize> {
+        match self.current {
+            Some(current) if matches.get(current) == Some(&offset) => {
+                let len = matches.len();
+                Some(match direction {
+                    Direction::Forward => (current + 1) % len,
+                    Direction::Backward => (current + len - 1) % len,
+                })
+            }
+            _ => self.find(matches, offset, direction, true),
+        }
+    }
+
+    /// Highlights every match in `text`, the current one in a different color.
+    pub fn highlight(&self, text: Text<'static>, theme: &Theme) -> Text<'static> {
+        let mut match_index = 0;
+        let lines = text
+            .lines
+            .into_iter()
+            .map(|line| {
+                let ranges = match_ranges(&self.regex, &line_text(&line));
+                let current = self
+                    .current
+                    .and_then(|current| current.checked_sub(match_index))
+                    .filter(|current| *current < ranges.len());
+                match_index += ranges.len();
+                highlight_line(line, &ranges, current, theme)
+            })
+            .collect::<Vec<_>>();
+        Text::from(lines)
+    }
+}
+
+/// Highlights the byte `ranges` of `line`, splitting its spans where needed.
+fn highlight_line(
+    line: Spans<'static>,
+    ranges: &[Range<usize>],
+    current: Option<usize>,
+    theme: &Theme,
+) -> Spans<'static> {
+    if ranges.is_empty() {
+        return line;
+    }
+    let match_style = Style::default().fg(Color::Black).bg(theme.search_match);
+    let current_style = Style::default().fg(Color::Black).bg(theme.search_current);
+
+    let mut spans = vec![];
+    let mut offset = 0;
+    for span in line.0 {
+        let content = span.content.as_ref();
+        let (start, end) = (offset, offset + content.len());
+        offset = end;
+
+        // Split the span wherever a match starts or ends
+        let mut points = vec![start, end];
+        for range in ranges {
+            points.extend(
+                [range.start, range.end]
+                    .into_iter()
+                    .filter(|point| *point > start && *point < end),
+            );
+        }
+        points.sort_unstable();
+        points.dedup();
+
+        for piece in points.windows(2) {
+            let (piece_start, piece_end) = (piece[0], piece[1]);
+            let style = match ranges
+                .iter()
+                .position(|range| range.start <= piece_start && piece_end <= range.end)
+            {
+                Some(index) if current == Some(index) => current_style,
+                Some(_) => match_style,
+                None => span.style,
+            };
+            spans.push(Span::styled(
+                content[piece_start - start..piece_end - start].to_owned(),
+                style,
+            ));
+        }
+    }
+    Spans::from(spans)
+}
+
+pub fn line_text(line: &Spans) -> String {
+    line.0.iter().map(|span| span.content.as_ref()).collect()
+}
+
+/// Byte ranges of the matches in `line`. Empty matches are ignored, they can't be highlighted.
+fn match_ranges(regex: &Regex, line: &str) -> Vec<Range<usize>> {
+    regex
+        .find_iter(line)
+        .map(|found| found.range())
+        .filter(|range| !range.is_empty())
+        .collect()
+}
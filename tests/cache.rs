@@ -0,0 +1,187 @@
+use ce::cache::{Cache, DEFAULT_TTL};
+use ce::compiler_explorer::{AsmOutput, CompilationResult, CompileOptions, InstructionDocs};
+
+use std::path::PathBuf;
+
+fn cache_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ce-cache-test-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn result(text: &str) -> CompilationResult {
+    CompilationResult {
+        code: 0,
+        stdout: vec![],
+        stderr: vec![],
+        asm: vec![AsmOutput {
+            text: text.to_owned(),
+            source: Some((None, 1)),
+            ..Default::default()
+        }],
+        execution: None,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn key_depends_on_every_input() {
+    let options = CompileOptions::default();
+    let key = Cache::key("https://godbolt.org", "clang_trunk", "int x;", &options);
+
+    assert_eq!(
+        key,
+        Cache::key("https://godbolt.org", "clang_trunk", "int x;", &options)
+    );
+    assert_ne!(
+        key,
+        Cache::key("http://localhost", "clang_trunk", "int x;", &options)
+    );
+    assert_ne!(
+        key,
+        Cache::key("https://godbolt.org", "gcc_trunk", "int x;", &options)
+    );
+    assert_ne!(
+        key,
+        Cache::key("https://godbolt.org", "clang_trunk", "int y;", &options)
+    );
+
+    let mut other_options = options.clone();
+    other_options.arguments.push("-O2".to_owned());
+    assert_ne!(
+        key,
+        Cache::key(
+            "https://godbolt.org",
+            "clang_trunk",
+            "int x;",
+            &other_options
+        )
+    );
+
+    let mut other_options = options;
+    other_options.filters.intel = false;
+    assert_ne!(
+        key,
+        Cache::key(
+            "https://godbolt.org",
+            "clang_trunk",
+            "int x;",
+            &other_options
+        )
+    );
+}
+
+#[test]
+fn roundtrip() {
+    let dir = cache_dir("roundtrip");
+    let cache = Cache::with_dir(dir.clone(), DEFAULT_TTL, 1024 * 1024);
+
+    assert!(cache.get("0123").is_none());
+    cache.put("0123", &result("main:"));
+
+    let cached = cache.get("0123").unwrap();
+    assert_eq!(cached.asm[0].text, "main:");
+    assert_eq!(cached.asm[0].source, Some((None, 1)));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn concurrent_writers_do_not_clash() {
+    let dir = cache_dir("concurrent");
+    let cache = Cache::with_dir(dir.clone(), DEFAULT_TTL, 1024 * 1024);
+
+    // Entries of different sizes, so that interleaved writes would corrupt the entry
+    let writers: Vec<_> = (1..=8)
+        .map(|writer| {
+            let cache = cache.clone();
+            std::thread::spawn(move || {
+                for _ in 0..50 {
+                    cache.put("0123", &result(&"x".repeat(writer * 10_000)));
+                    assert!(cache.get("0123").is_some());
+                }
+            })
+        })
+        .collect();
+    for writer in writers {
+        writer.join().unwrap();
+    }
+
+    // No temporary file is left behind
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn evicts_when_full() {
+    let dir = cache_dir("evicts_when_full");
+    let cache = Cache::with_dir(dir.clone(), DEFAULT_TTL, 1024 * 1024);
+    cache.put("first", &result("first:"));
+    assert!(cache.get("first").is_some());
+
+    // Large enough for a single entry only
+    let entry_size = std::fs::metadata(dir.join("first.json")).unwrap().len();
+    let cache = Cache::with_dir(dir.clone(), DEFAULT_TTL, entry_size + 8);
+
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    cache.put("second", &result("second"));
+    assert!(cache.get("first").is_none());
+    assert!(cache.get("second").is_some());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn ignores_corrupt_entries() {
+    let dir = cache_dir("ignores_corrupt_entries");
+    let cache = Cache::with_dir(dir.clone(), DEFAULT_TTL, 1024 * 1024);
+
+    cache.put("entry", &result("main:"));
+    std::fs::write(dir.join("entry.json"), "not json").unwrap();
+    assert!(cache.get("entry").is_none());
+    assert!(!dir.join("entry.json").exists());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn stores_instruction_docs() {
+    let dir = cache_dir("docs");
+    let cache = Cache::with_dir(dir.clone(), DEFAULT_TTL, 1024 * 1024);
+    let key = Cache::docs_key("https://godbolt.org", "amd64", "MOV");
+    assert_eq!(key, Cache::docs_key("https://godbolt.org", "amd64", "mov"));
+    assert_ne!(
+        key,
+        Cache::docs_key("https://godbolt.org", "aarch64", "mov")
+    );
+
+    let docs = InstructionDocs {
+        tooltip: "Move".to_owned(),
+        html: "<p>Move</p>".to_owned(),
+        url: None,
+    };
+    cache.put_value(&key, &docs);
+
+    assert_eq!(cache.get_value::<InstructionDocs>(&key), Some(docs));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn keys_are_stable() {
+    // Changing these drops every entry users have, only do it when the key has to change
+    assert_eq!(
+        Cache::key(
+            "https://godbolt.org",
+            "clang_trunk",
+            "int x;",
+            &CompileOptions::default()
+        ),
+        "b93275c4cb9faeb92e74f358a98e1d6e2f5c44ea412cd2ca24bc421bb6745b45"
+    );
+    assert_eq!(
+        Cache::docs_key("https://godbolt.org", "amd64", "MOV"),
+        "8f90fcf1a85f7eb8d5dbf7bb6212bd13203d3a73226b768fc978a8c58c3b4444"
+    );
+}
@@ -0,0 +1,148 @@
+mod common;
+
+use ce::cfg::{build, render};
+use ce::compiler_explorer::AsmOutput;
+use common::asm;
+
+fn sum() -> Vec<AsmOutput> {
+    asm(&[
+        "sum(int*, int):",
+        "        test    esi, esi",
+        "        jle     .L5",
+        "        mov     ecx, 0",
+        ".L4:",
+        "        add     ecx, eax",
+        "        cmp     rdx, rsi",
+        "        jne     .L4",
+        ".L2:",
+        "        mov     eax, ecx",
+        "        ret",
+        ".L5:",
+        "        mov     ecx, 0",
+        "        jmp     .L2",
+        ".LC0:",
+        "        .string \"hello\"",
+        "main:",
+        "        xor     eax, eax",
+        "        ret",
+    ])
+}
+
+#[test]
+fn splits_functions_into_blocks() {
+    let functions = build(&sum());
+
+    assert_eq!(functions.len(), 2);
+    assert_eq!(functions[0].name, "sum(int*, int)");
+    assert_eq!(functions[1].name, "main");
+
+    let blocks = &functions[0].blocks;
+    let labels: Vec<_> = blocks.iter().map(|block| block.label.as_deref()).collect();
+    assert_eq!(
+        labels,
+        vec![
+            Some("sum(int*, int)"),
+            None,
+            Some(".L4"),
+            Some(".L2"),
+            Some(".L5")
+        ]
+    );
+    assert_eq!(blocks[0].lines, vec!["test    esi, esi", "jle     .L5"]);
+    // Conditional jumps fall through first
+    assert_eq!(blocks[0].successors, vec![1, 4]);
+    assert_eq!(blocks[1].successors, vec![2]);
+    assert_eq!(blocks[2].successors, vec![3, 2]);
+    assert!(blocks[3].successors.is_empty());
+    assert_eq!(blocks[4].successors, vec![3]);
+    // Data after the last block is not code
+    assert_eq!(blocks[4].lines, vec!["mov     ecx, 0", "jmp     .L2"]);
+}
+
+#[test]
+fn splits_disassembly_at_jump_addresses() {
+    let mut asm = asm(&[
+        "sum(int*, int):",
+        "        test   esi,esi",
+        "        jle    2c <sum(int*, int)+0x26>",
+        "        add    ecx,eax",
+        "        jne    19 <sum(int*, int)+0x13>",
+        "        ret",
+        "        mov    ecx,0x0",
+    ]);
+    for (line, address) in asm
+        .iter_mut()
+        .skip(1)
+        .zip([0x0, 0x2, 0x19, 0x1b, 0x1f, 0x2c])
+    {
+        line.address = Some(address);
+    }
+
+    let functions = build(&asm);
+
+    let blocks = &functions[0].blocks;
+    assert_eq!(blocks.len(), 4);
+    assert_eq!(blocks[0].successors, vec![1, 3]);
+    assert_eq!(
+        blocks[1].lines,
+        vec!["add    ecx,eax", "jne    19 <sum(int*, int)+0x13>"]
+    );
+    assert_eq!(blocks[1].successors, vec![2, 1]);
+    assert!(blocks[2].successors.is_empty());
+}
+
+#[test]
+fn indirect_jumps_have_no_successors() {
+    let functions = build(&asm(&["f:", "        jmp     rax", "        nop"]));
+
+    assert_eq!(functions[0].blocks.len(), 2);
+    assert!(functions[0].blocks[0].successors.is_empty());
+}
+
+#[test]
+fn renders_loops_in_the_margin() {
+    let functions = build(&asm(&[
+        "f:",
+        "        xor     eax, eax",
+        ".L2:",
+        "        add     eax, 1",
+        "        jne     .L2",
+        "        ret",
+    ]));
+
+    assert_eq!(
+        render(&functions[0]),
+        vec![
+            "  ┌─ f ──────────────┐",
+            "  │ xor     eax, eax │",
+            "  └──────────────────┘",
+            "┌►┌─ .L2 ────────────┐",
+            "│ │ add     eax, 1   │",
+            "│ │ jne     .L2      │",
+            "│ └──────────────────┘",
+            "└─  ↑ .L2",
+            "  ┌─ B2 ─────────────┐",
+            "  │ ret              │",
+            "  └──────────────────┘",
+        ]
+    );
+}
+
+#[test]
+fn riscv_calls_do_not_end_blocks() {
+    let functions = build(&asm(&[
+        "f:",
+        "        jal     ra, g",
+        "        beqz    a0, .L2",
+        "        jalr    a1",
+        ".L2:",
+        "        jr      ra",
+    ]));
+
+    let blocks = &functions[0].blocks;
+    assert_eq!(blocks.len(), 3);
+    assert_eq!(blocks[0].lines, vec!["jal     ra, g", "beqz    a0, .L2"]);
+    assert_eq!(blocks[0].successors, vec![1, 2]);
+    assert_eq!(blocks[1].successors, vec![2]);
+    assert!(blocks[2].successors.is_empty());
+}
@@ -0,0 +1,14 @@
+//! Fixtures shared by the integration tests.
+
+use ce::compiler_explorer::AsmOutput;
+
+/// ASM output with one line per entry of `lines`, without addresses or source locations.
+pub fn asm(lines: &[&str]) -> Vec<AsmOutput> {
+    lines
+        .iter()
+        .map(|line| AsmOutput {
+            text: line.to_string(),
+            ..Default::default()
+        })
+        .collect()
+}
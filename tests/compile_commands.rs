@@ -0,0 +1,325 @@
+use ce::compile_commands::{bundle_headers, lookup, Entry, Error};
+
+use std::path::{Path, PathBuf};
+
+fn project_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "ce-compile-commands-test-{}-{}",
+        std::process::id(),
+        name
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write(path: &Path, contents: &str) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, contents).unwrap();
+}
+
+fn entry(arguments: &[&str]) -> Entry {
+    Entry {
+        directory: PathBuf::from("/work/build"),
+        file: PathBuf::from("../src/foo.cpp"),
+        arguments: arguments
+            .iter()
+            .map(|argument| argument.to_string())
+            .collect(),
+        command: None,
+    }
+}
+
+#[test]
+fn drops_outputs_and_the_file() {
+    let flags = entry(&[
+        "/usr/bin/c++",
+        "-DNDEBUG",
+        "-D",
+        "VERSION=2",
+        "-O2",
+        "-std=gnu++20",
+        "-MD",
+        "-MT",
+        "foo.o",
+        "-MF",
+        "foo.o.d",
+        "-fdiagnostics-color=always",
+        "-o",
+        "CMakeFiles/foo.dir/src/foo.cpp.o",
+        "-c",
+        "../src/foo.cpp",
+    ])
+    .flags(false);
+
+    assert_eq!(
+        flags.arguments,
+        ["-DNDEBUG", "-D", "VERSION=2", "-O2", "-std=gnu++20"]
+    );
+    assert!(flags.include_dirs.is_empty());
+}
+
+#[test]
+fn translates_paths() {
+    let entry = entry(&[
+        "c++",
+        "-I../include",
+        "-isystem",
+        "/opt/boost/include",
+        "-include",
+        "config.h",
+        "-include-pch",
+        "pch.h.pch",
+        "--sysroot=/opt/sysroot",
+        "-Wall",
+        "-c",
+        "../src/foo.cpp",
+    ]);
+
+    let remote = entry.flags(false);
+    assert_eq!(remote.arguments, ["-Wall"]);
+    assert_eq!(
+        remote.include_dirs,
+        [
+            PathBuf::from("/work/build/../include"),
+            PathBuf::from("/opt/boost/include")
+        ]
+    );
+    assert_eq!(
+        remote.forced_includes,
+        [PathBuf::from("/work/build/config.h")]
+    );
+
+    let local = entry.flags(true);
+    assert_eq!(
+        local.arguments,
+        [
+            "-I/work/build/../include",
+            "-isystem/opt/boost/include",
+            "-include/work/build/config.h",
+            "-include-pch/work/build/pch.h.pch",
+            "--sysroot=/opt/sysroot",
+            "-Wall"
+        ]
+    );
+    assert!(local.include_dirs.is_empty());
+}
+
+#[test]
+fn keeps_values_of_flags() {
+    let flags = entry(&[
+        "c++",
+        "--param",
+        "max-inline-insns-single=100",
+        "-Xarch_x86_64",
+        "-mavx2",
+        "-mllvm",
+        "-inline-threshold=500",
+        "-target-feature",
+        "+avx2",
+        "-O2",
+        "/work/src/foo.cpp",
+    ])
+    .flags(false);
+
+    assert_eq!(
+        flags.arguments,
+        [
+            "--param",
+            "max-inline-insns-single=100",
+            "-Xarch_x86_64",
+            "-mavx2",
+            "-mllvm",
+            "-inline-threshold=500",
+            "-target-feature",
+            "+avx2",
+            "-O2",
+        ]
+    );
+}
+
+#[test]
+fn tells_linker_flags_from_directories() {
+    let entry = entry(&[
+        "c++",
+        "-Bstatic",
+        "-B",
+        "tools",
+        "-B/opt/gcc/bin",
+        "-Bdynamic",
+        "-c",
+        "../src/foo.cpp",
+    ]);
+
+    assert_eq!(entry.flags(false).arguments, ["-Bstatic", "-Bdynamic"]);
+    assert_eq!(
+        entry.flags(true).arguments,
+        [
+            "-Bstatic",
+            "-B/work/build/tools",
+            "-B/opt/gcc/bin",
+            "-Bdynamic"
+        ]
+    );
+}
+
+#[test]
+fn joined_paths_only_exist_here() {
+    let entry = entry(&[
+        "c++",
+        "-fprofile-use=../pgo/foo.profdata",
+        "-fsanitize-blacklist=/work/ignore.txt",
+        "-fdebug-prefix-map=/work=.",
+        "-fprofile-use",
+        "-fsanitize=address",
+        "-c",
+        "../src/foo.cpp",
+    ]);
+
+    assert_eq!(
+        entry.flags(false).arguments,
+        ["-fprofile-use", "-fsanitize=address"]
+    );
+    assert_eq!(
+        entry.flags(true).arguments,
+        [
+            "-fprofile-use=/work/build/../pgo/foo.profdata",
+            "-fsanitize-blacklist=/work/ignore.txt",
+            "-fdebug-prefix-map=/work=.",
+            "-fprofile-use",
+            "-fsanitize=address"
+        ]
+    );
+}
+
+#[test]
+fn splits_commands() {
+    let entry = Entry {
+        command: Some(r#"cc -DNAME="a b" -DQUOTE=\" '-DSINGLE=x y' -c foo.c"#.to_owned()),
+        ..entry(&[])
+    };
+
+    assert_eq!(
+        entry.arguments(),
+        [
+            "cc",
+            "-DNAME=a b",
+            "-DQUOTE=\"",
+            "-DSINGLE=x y",
+            "-c",
+            "foo.c"
+        ]
+    );
+}
+
+#[test]
+fn finds_the_entry_of_a_file() {
+    let dir = project_dir("lookup");
+    write(&dir.join("src/foo.cpp"), "");
+    write(&dir.join("src/bar.cpp"), "");
+    let database = dir.join("build/compile_commands.json");
+    write(
+        &database,
+        &serde_json::json!([
+            {
+                "directory": dir.join("build"),
+                "file": "../src/foo.cpp",
+                "command": "c++ -O1 -c ../src/foo.cpp",
+            },
+            {
+                "directory": dir.join("build"),
+                "file": dir.join("src/bar.cpp"),
+                "arguments": ["c++", "-O3", "-c", "../src/bar.cpp"],
+            },
+        ])
+        .to_string(),
+    );
+
+    let foo = lookup(&database, &dir.join("src/foo.cpp")).unwrap();
+    assert_eq!(foo.flags(false).arguments, ["-O1"]);
+    let bar = lookup(&database, &dir.join("build/../src/bar.cpp")).unwrap();
+    assert_eq!(bar.flags(false).arguments, ["-O3"]);
+
+    write(&dir.join("src/baz.cpp"), "");
+    assert!(matches!(
+        lookup(&database, &dir.join("src/baz.cpp")),
+        Err(Error::NotFound(..))
+    ));
+}
+
+#[test]
+fn bundles_local_headers() {
+    let dir = project_dir("bundle");
+    write(
+        &dir.join("src/util.h"),
+        "#pragma once\n#include \"detail/impl.h\"\n",
+    );
+    write(
+        &dir.join("src/detail/impl.h"),
+        "#include \"../util.h\"\n#include <common.h>\n",
+    );
+    write(
+        &dir.join("include/common.h"),
+        "#include <vector>\nint common();\n",
+    );
+    let source = "#include <cstdio>\n  #  include \"util.h\"\n#include \"missing.h\"\n";
+
+    let mut files = bundle_headers(source, &dir.join("src"), &[dir.join("include")], &[]).files;
+    files.sort_by(|a, b| a.filename.cmp(&b.filename));
+
+    let names: Vec<_> = files.iter().map(|file| file.filename.as_str()).collect();
+    assert_eq!(names, ["common.h", "detail/impl.h", "util.h"]);
+    assert_eq!(files[0].contents, "#include <vector>\nint common();\n");
+}
+
+#[test]
+fn bundles_forced_includes_and_reports_headers_above_the_source() {
+    let dir = project_dir("forced");
+    write(&dir.join("build/config.h"), "#include \"version.h\"\n");
+    write(&dir.join("build/version.h"), "#define VERSION 2\n");
+    write(&dir.join("common.h"), "int common();\n");
+    let source = "#include \"../common.h\"\n";
+    write(&dir.join("src/foo.cpp"), source);
+
+    let bundle = bundle_headers(source, &dir.join("src"), &[], &[dir.join("build/config.h")]);
+
+    let names: Vec<_> = bundle
+        .files
+        .iter()
+        .map(|file| file.filename.as_str())
+        .collect();
+    assert_eq!(names, ["config.h", "version.h"]);
+    assert_eq!(bundle.skipped, ["../common.h"]);
+}
+
+#[test]
+fn forced_includes_with_the_same_name_are_kept_apart() {
+    let dir = project_dir("forced-names");
+    write(&dir.join("a/config.h"), "#include \"detail.h\"\n");
+    write(&dir.join("a/detail.h"), "int a;\n");
+    write(&dir.join("b/config.h"), "int b;\n");
+
+    let bundle = bundle_headers(
+        "",
+        &dir,
+        &[],
+        &[
+            dir.join("a/config.h"),
+            dir.join("missing.h"),
+            dir.join("b/config.h"),
+        ],
+    );
+
+    assert_eq!(bundle.forced_includes, ["config.h", "include-2/config.h"]);
+    let mut names: Vec<_> = bundle
+        .files
+        .iter()
+        .map(|file| file.filename.as_str())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["config.h", "detail.h", "include-2/config.h"]);
+    assert_eq!(
+        bundle.skipped,
+        [dir.join("missing.h").display().to_string()]
+    );
+}
@@ -0,0 +1,532 @@
+mod mock_server;
+
+use ce::compiler_explorer::{
+    parse_asm, parse_stream, CompileOptions, CompilerExplorerClient, Error, Filters, GccDumpPass,
+    OptRemark, ProduceOptions, RemarkKind, SourceFile,
+};
+use mock_server::MockServer;
+use serde_json::{json, Value};
+
+use std::time::Duration;
+
+const SOURCE: &str = "int main() { return 32; }";
+
+fn client(server: &MockServer) -> CompilerExplorerClient {
+    CompilerExplorerClient::new(&server.url(), Duration::from_secs(5)).unwrap()
+}
+
+#[tokio::test]
+async fn compile_success() {
+    let server = MockServer::start().await;
+    let options = CompileOptions {
+        arguments: vec!["-O2".to_owned(), "-std=c++20".to_owned()],
+        ..Default::default()
+    };
+
+    let result = client(&server)
+        .compile("success", SOURCE, &options)
+        .await
+        .unwrap();
+
+    assert_eq!(result.code, 0);
+    assert!(result.execution.is_none());
+    assert_eq!(result.asm.len(), 5);
+    assert_eq!(result.asm[0].text, "main:");
+    assert_eq!(result.asm[0].source, None);
+    assert_eq!(result.asm[2].text, "        mov     eax, 32");
+    assert_eq!(result.asm[2].source, Some((None, 32)));
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].path, "/api/compiler/success/compile");
+    let body = requests[0].body.as_ref().unwrap();
+    assert_eq!(body["source"], SOURCE);
+    assert_eq!(body["options"]["userArguments"], "-O2 -std=c++20");
+    assert!(body["options"].get("filters").is_none());
+}
+
+#[tokio::test]
+async fn compile_sends_headers() {
+    let server = MockServer::start().await;
+    let options = CompileOptions {
+        files: vec![SourceFile {
+            filename: "util/square.h".to_owned(),
+            contents: "int square(int);".to_owned(),
+        }],
+        ..Default::default()
+    };
+
+    client(&server)
+        .compile("success", SOURCE, &options)
+        .await
+        .unwrap();
+
+    let body = server.requests()[0].body.clone().unwrap();
+    assert_eq!(body["files"][0]["filename"], "util/square.h");
+    assert_eq!(body["files"][0]["contents"], "int square(int);");
+}
+
+#[tokio::test]
+async fn compile_binary() {
+    let server = MockServer::start().await;
+    let options = CompileOptions {
+        filters: Filters {
+            binary: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let result = client(&server)
+        .compile("success", SOURCE, &options)
+        .await
+        .unwrap();
+
+    assert_eq!(result.asm[0].address, None);
+    assert!(result.asm[0].opcodes.is_empty());
+    assert_eq!(result.asm[2].address, Some(4465));
+    assert_eq!(result.asm[2].opcodes, vec!["b8", "20", "00", "00", "00"]);
+
+    let body = server.requests()[0].body.clone().unwrap();
+    assert_eq!(body["options"]["filters"]["binary"], true);
+    assert_eq!(body["options"]["filters"]["binaryObject"], false);
+}
+
+#[tokio::test]
+async fn extra_outputs_are_only_requested_when_needed() {
+    let server = MockServer::start().await;
+
+    let result = client(&server)
+        .compile("success", SOURCE, &CompileOptions::default())
+        .await
+        .unwrap();
+
+    assert!(result.ir.is_none());
+    assert!(result.ast.is_none());
+    assert!(result.gcc_dump.is_none());
+    assert!(result.opt_pipeline.is_none());
+    let body = server.requests()[0].body.clone().unwrap();
+    assert_eq!(body["options"]["compilerOptions"], serde_json::json!({}));
+}
+
+#[tokio::test]
+async fn compile_extra_outputs() {
+    let server = MockServer::start().await;
+    let options = CompileOptions {
+        produce: ProduceOptions {
+            ir: true,
+            ast: true,
+            gcc_dump: true,
+            gcc_dump_pass: None,
+            opt_pipeline: true,
+            opt_info: false,
+        },
+        ..Default::default()
+    };
+
+    let result = client(&server)
+        .compile("success", SOURCE, &options)
+        .await
+        .unwrap();
+
+    let ir = result.ir.unwrap();
+    assert_eq!(ir.len(), 3);
+    assert_eq!(ir[1].text, "  ret i32 32");
+    assert_eq!(ir[1].source, Some((None, 32)));
+    assert_eq!(result.ast.unwrap()[0].text, "TranslationUnitDecl");
+
+    let gcc_dump = result.gcc_dump.unwrap();
+    assert_eq!(gcc_dump.passes.len(), 2);
+    assert_eq!(gcc_dump.passes[1].filename_suffix, "t.optimized");
+    assert!(gcc_dump.selected_pass.is_none());
+
+    let opt_pipeline = result.opt_pipeline.unwrap();
+    assert_eq!(opt_pipeline.len(), 1);
+    assert_eq!(opt_pipeline[0].name, "main");
+    assert_eq!(opt_pipeline[0].passes.len(), 2);
+    assert!(opt_pipeline[0].passes[0].changed);
+    assert!(!opt_pipeline[0].passes[1].changed);
+    assert_eq!(opt_pipeline[0].passes[0].before.len(), 2);
+    assert_eq!(
+        opt_pipeline[0].passes[0].after,
+        vec!["  ret i32 32".to_owned()]
+    );
+
+    let body = server.requests()[0].body.clone().unwrap();
+    let compiler_options = &body["options"]["compilerOptions"];
+    assert_eq!(compiler_options["produceAst"], true);
+    assert!(compiler_options["produceIr"].is_object());
+    assert!(compiler_options["produceOptPipeline"].is_object());
+    assert!(compiler_options["produceGccDump"]["pass"].is_null());
+}
+
+#[tokio::test]
+async fn optimization_remarks() {
+    let server = MockServer::start().await;
+    let options = CompileOptions {
+        produce: ProduceOptions {
+            opt_info: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let result = client(&server)
+        .compile("success", SOURCE, &options)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        result.opt_remarks.unwrap(),
+        vec![
+            OptRemark {
+                kind: RemarkKind::Passed,
+                pass: "inline".to_owned(),
+                function: Some("main".to_owned()),
+                location: Some((7, 10)),
+                message: "square inlined into main".to_owned(),
+            },
+            OptRemark {
+                kind: RemarkKind::Missed,
+                pass: "loop-vectorize".to_owned(),
+                function: Some("sum".to_owned()),
+                location: Some((3, 5)),
+                message: "loop not vectorized".to_owned(),
+            },
+        ]
+    );
+    let body = server.requests()[0].body.clone().unwrap();
+    assert_eq!(body["options"]["compilerOptions"]["produceOptInfo"], true);
+}
+
+#[tokio::test]
+async fn gcc_dump_of_selected_pass() {
+    let server = MockServer::start().await;
+    let pass = GccDumpPass {
+        name: "optimized (tree)".to_owned(),
+        command_prefix: "-fdump-tree-optimized".to_owned(),
+        filename_suffix: "t.optimized".to_owned(),
+    };
+    let options = CompileOptions {
+        produce: ProduceOptions {
+            gcc_dump: true,
+            gcc_dump_pass: Some(pass.clone()),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let result = client(&server)
+        .compile("success", SOURCE, &options)
+        .await
+        .unwrap();
+
+    let gcc_dump = result.gcc_dump.unwrap();
+    assert_eq!(gcc_dump.selected_pass, Some(pass));
+    assert_eq!(gcc_dump.output, "Dump of optimized (tree)");
+}
+
+#[tokio::test]
+async fn compile_and_execute() {
+    let server = MockServer::start().await;
+    let options = CompileOptions {
+        execute: true,
+        ..Default::default()
+    };
+
+    let result = client(&server)
+        .compile("success", SOURCE, &options)
+        .await
+        .unwrap();
+
+    let execution = result.execution.unwrap();
+    assert_eq!(execution.code, 32);
+    assert_eq!(execution.stdout.len(), 1);
+    assert_eq!(execution.stdout[0].text, "Hi there!!");
+    assert!(execution.stderr.is_empty());
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn execute_only() {
+    let server = MockServer::start().await;
+
+    let execution = client(&server)
+        .execute("success", SOURCE, &CompileOptions::default())
+        .await
+        .unwrap();
+
+    assert_eq!(execution.code, 32);
+    assert_eq!(execution.stdout[0].text, "Hi there!!");
+    let body = server.requests()[0].body.clone().unwrap();
+    assert_eq!(body["options"]["compilerOptions"]["skipAsm"], true);
+}
+
+#[tokio::test]
+async fn compile_error_is_not_an_api_error() {
+    let server = MockServer::start().await;
+
+    let result = client(&server)
+        .compile("compile_error", SOURCE, &CompileOptions::default())
+        .await
+        .unwrap();
+
+    assert_eq!(result.code, 1);
+    assert_eq!(result.stderr.len(), 2);
+    assert_eq!(
+        result.stderr[0].tag,
+        Some((3, "expected ';' after top level declarator".to_owned()))
+    );
+    assert_eq!(result.stderr[1].tag, None);
+    assert_eq!(result.asm[0].text, "<Compilation failed>");
+}
+
+#[tokio::test]
+async fn http_error_keeps_status_and_body() {
+    let server = MockServer::start().await;
+
+    let error = client(&server)
+        .compile("internal_error", SOURCE, &CompileOptions::default())
+        .await
+        .unwrap_err();
+
+    match error {
+        Error::InvalidHttpResponse(status, body) => {
+            assert_eq!(status, reqwest::StatusCode::INTERNAL_SERVER_ERROR);
+            assert_eq!(body, "Internal error");
+        }
+        e => panic!("Unexpected error {:?}", e),
+    }
+}
+
+#[tokio::test]
+async fn unknown_compiler() {
+    let server = MockServer::start().await;
+
+    let error = client(&server)
+        .execute("does_not_exist", SOURCE, &CompileOptions::default())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        error,
+        Error::InvalidHttpResponse(reqwest::StatusCode::NOT_FOUND, _)
+    ));
+}
+
+#[tokio::test]
+async fn malformed_json() {
+    let server = MockServer::start().await;
+
+    let error = client(&server)
+        .compile("malformed_json", SOURCE, &CompileOptions::default())
+        .await
+        .unwrap_err();
+
+    match error {
+        Error::HttpError(e) => assert!(e.is_decode()),
+        e => panic!("Unexpected error {:?}", e),
+    }
+}
+
+#[tokio::test]
+async fn missing_error_code() {
+    let server = MockServer::start().await;
+
+    let error = client(&server)
+        .compile("missing_code", SOURCE, &CompileOptions::default())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, Error::InvalidErrorCode));
+}
+
+#[tokio::test]
+async fn invalid_asm_is_dropped() {
+    let server = MockServer::start().await;
+
+    let result = client(&server)
+        .compile("invalid_asm", SOURCE, &CompileOptions::default())
+        .await
+        .unwrap();
+
+    assert_eq!(result.code, 0);
+    assert!(result.asm.is_empty());
+}
+
+#[test]
+fn parse_errors() {
+    assert!(matches!(
+        parse_asm(&json!([{ "source": null }])),
+        Err(Error::InvalidAsm)
+    ));
+    assert!(matches!(
+        parse_asm(&json!([{ "text": "ret", "source": { "line": null } }])),
+        Err(Error::InvalidAsm)
+    ));
+    assert!(matches!(parse_asm(&Value::Null), Err(Error::NoAsmFound)));
+    assert!(matches!(
+        parse_stream(&json!([{ "tag": null }])),
+        Err(Error::InvalidStream)
+    ));
+    assert!(matches!(
+        parse_stream(&json!({ "text": "a" })),
+        Err(Error::InvalidStream)
+    ));
+    assert!(matches!(
+        parse_stream(&Value::Null),
+        Err(Error::NoStreamFound)
+    ));
+}
+
+#[tokio::test]
+async fn lists_compilers() {
+    let server = MockServer::start().await;
+
+    let compilers = client(&server).compilers().await.unwrap();
+
+    assert_eq!(compilers.len(), 2);
+    assert_eq!(compilers[0].id, "success");
+    assert_eq!(compilers[0].name, "mock clang");
+    assert_eq!(compilers[0].lang, "c++");
+    assert_eq!(compilers[0].instruction_set.as_deref(), Some("amd64"));
+}
+
+#[tokio::test]
+async fn lists_languages() {
+    let server = MockServer::start().await;
+
+    let languages = client(&server).languages().await.unwrap();
+
+    let ids: Vec<_> = languages
+        .iter()
+        .map(|language| language.id.as_str())
+        .collect();
+    assert_eq!(ids, ["c++", "c"]);
+    assert_eq!(languages[0].name, "C++");
+    assert!(languages[1].extensions.contains(&".c".to_owned()));
+}
+
+#[tokio::test]
+async fn shortens_sessions() {
+    let server = MockServer::start().await;
+    let options = CompileOptions {
+        arguments: vec!["-O2".to_owned()],
+        ..Default::default()
+    };
+
+    let url = client(&server)
+        .shorten("c++", "success", SOURCE, &options)
+        .await
+        .unwrap();
+
+    assert_eq!(url, "https://godbolt.org/z/mock");
+    let requests = server.requests();
+    assert_eq!(requests[0].path, "/api/shortener");
+    let session = &requests[0].body.as_ref().unwrap()["sessions"][0];
+    assert_eq!(session["language"], "c++");
+    assert_eq!(session["source"], SOURCE);
+    assert_eq!(session["compilers"][0]["id"], "success");
+    assert_eq!(session["compilers"][0]["options"], "-O2");
+}
+
+#[tokio::test]
+async fn instruction_set_of_compiler() {
+    let server = MockServer::start().await;
+    let client = client(&server);
+
+    assert_eq!(
+        client.instruction_set("success").await.unwrap().as_deref(),
+        Some("amd64")
+    );
+    assert_eq!(client.instruction_set("missing").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn instruction_docs() {
+    let server = MockServer::start().await;
+
+    let docs = client(&server)
+        .instruction_docs("amd64", "MOV")
+        .await
+        .unwrap();
+
+    assert_eq!(
+        docs.tooltip,
+        "Copies the second operand to the first operand."
+    );
+    assert_eq!(
+        docs.text(),
+        "Copies the second operand (source operand) to the first operand (destination \
+         operand).\n\nBoth operands must be the same size & cannot both be memory locations."
+    );
+    assert_eq!(
+        docs.url.as_deref(),
+        Some("https://www.felixcloutier.com/x86/mov")
+    );
+    assert_eq!(server.requests()[0].path, "/api/asm/amd64/mov");
+}
+
+#[tokio::test]
+async fn unknown_instruction() {
+    let server = MockServer::start().await;
+    let client = client(&server);
+
+    let error = client.instruction_docs("amd64", "bogus").await.unwrap_err();
+    assert!(matches!(error, Error::UnknownOpcode(opcode) if opcode == "bogus"));
+
+    let error = client.instruction_docs("z80", "ld").await.unwrap_err();
+    assert!(matches!(
+        error,
+        Error::InvalidHttpResponse(reqwest::StatusCode::NOT_FOUND, _)
+    ));
+}
+
+#[tokio::test]
+async fn retries_server_errors() {
+    let server = MockServer::start().await;
+
+    let result = client(&server)
+        .compile("flaky", SOURCE, &CompileOptions::default())
+        .await
+        .unwrap();
+
+    assert_eq!(result.code, 0);
+    assert_eq!(server.requests().len(), mock_server::FLAKY_FAILURES + 1);
+}
+
+#[tokio::test]
+async fn gives_up_when_rate_limited() {
+    let server = MockServer::start().await;
+
+    let error = client(&server)
+        .compile("rate_limited", SOURCE, &CompileOptions::default())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        error,
+        Error::InvalidHttpResponse(reqwest::StatusCode::TOO_MANY_REQUESTS, _)
+    ));
+    // The first attempt plus three retries
+    assert_eq!(server.requests().len(), 4);
+}
+
+#[tokio::test]
+async fn connection_refused() {
+    let server = MockServer::start().await;
+    let client = client(&server);
+    drop(server);
+    // Let the server task notice the shutdown and close the listener
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let error = client
+        .compile("success", SOURCE, &CompileOptions::default())
+        .await
+        .unwrap_err();
+
+    match error {
+        Error::HttpError(e) => assert!(e.is_connect()),
+        e => panic!("Unexpected error {:?}", e),
+    }
+}
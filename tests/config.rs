@@ -0,0 +1,112 @@
+use ce::config::{Config, Error};
+use ce::keymap::Action;
+use ce::layout::{Direction, Layout};
+use ce::theme::{self, Preset, Theme};
+
+use tui::style::Color;
+
+use std::path::PathBuf;
+
+fn config_file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "ce-config-test-{}-{}.toml",
+        std::process::id(),
+        name
+    ));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn loads_key_bindings() {
+    let path = config_file(
+        "keys",
+        r#"
+            [keys]
+            scroll_down = ["j", "ctrl-n"]
+            quit = ["q"]
+        "#,
+    );
+
+    let config = Config::load(Some(&path)).unwrap();
+
+    assert_eq!(config.keys.len(), 2);
+    assert_eq!(config.keys[&Action::ScrollDown], vec!["j", "ctrl-n"]);
+    assert_eq!(config.keys[&Action::Quit], vec!["q"]);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn empty_file_is_the_default_configuration() {
+    let path = config_file("empty", "");
+
+    assert_eq!(Config::load(Some(&path)).unwrap(), Config::default());
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn rejects_unknown_actions() {
+    let path = config_file("unknown", "[keys]\nlaunch_rockets = [\"x\"]\n");
+
+    assert!(matches!(Config::load(Some(&path)), Err(Error::Parse(..))));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn missing_explicit_file_is_an_error() {
+    let path = std::env::temp_dir().join("ce-config-test-does-not-exist.toml");
+
+    assert!(matches!(Config::load(Some(&path)), Err(Error::Io(..))));
+}
+
+#[test]
+fn loads_theme() {
+    let path = config_file(
+        "theme",
+        r##"
+            [theme]
+            preset = "light"
+            title = "light_blue"
+            cursor = "#102030"
+            selection = "236"
+        "##,
+    );
+
+    let theme = Config::load(Some(&path)).unwrap().theme.theme().unwrap();
+
+    assert_eq!(theme.title, Color::LightBlue);
+    assert_eq!(theme.cursor, Color::Rgb(0x10, 0x20, 0x30));
+    assert_eq!(theme.selection, Color::Indexed(236));
+    assert_eq!(theme.error, Theme::preset(Preset::Light).error);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn rejects_invalid_theme_colors() {
+    let path = config_file(
+        "bad-theme",
+        "[theme]\ntitle = \"#12345\"\nborders = \"red\"\n",
+    );
+    let config = Config::load(Some(&path)).unwrap();
+
+    assert!(matches!(
+        config.theme.theme(),
+        Err(theme::Error::InvalidColor(..) | theme::Error::UnknownColorName(..))
+    ));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn saves_layout_keeping_the_rest() {
+    let path = config_file("layout", "[keys]\nquit = [\"q\"]\n");
+    let mut layout = Layout::default();
+    layout.root.direction = Direction::Vertical;
+    layout.root.children[0].weight = 20;
+
+    Config::save_layout(&path, &layout).unwrap();
+    let config = Config::load(Some(&path)).unwrap();
+
+    assert_eq!(config.layout, Some(layout));
+    assert_eq!(config.keys[&Action::Quit], vec!["q"]);
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,71 @@
+use ce::editor::{Buffer, Motion};
+
+#[test]
+fn inserts_and_splits_lines() {
+    let mut buffer = Buffer::new("int f();\n");
+    buffer.set_cursor(0, 6);
+    buffer.insert('x');
+    assert_eq!(buffer.text(), "int f(x);\n");
+
+    buffer.move_cursor(Motion::LineEnd);
+    buffer.insert('\n');
+    for c in "int g();".chars() {
+        buffer.insert(c);
+    }
+    assert_eq!(buffer.text(), "int f(x);\nint g();\n");
+    assert_eq!(buffer.cursor(), (1, 8));
+}
+
+#[test]
+fn deleting_joins_lines() {
+    let mut buffer = Buffer::new("ab\ncd");
+    buffer.set_cursor(1, 0);
+    buffer.backspace();
+    assert_eq!(buffer.text(), "abcd\n");
+    assert_eq!(buffer.cursor(), (0, 2));
+
+    buffer.delete();
+    assert_eq!(buffer.text(), "abd\n");
+    buffer.move_cursor(Motion::LineEnd);
+    buffer.delete();
+    assert_eq!(buffer.text(), "abd\n");
+}
+
+#[test]
+fn columns_count_characters() {
+    let mut buffer = Buffer::new("// ñandú\n");
+    buffer.move_cursor(Motion::LineEnd);
+    assert_eq!(buffer.cursor(), (0, 8));
+    buffer.backspace();
+    buffer.insert('u');
+    assert_eq!(buffer.text(), "// ñandu\n");
+}
+
+#[test]
+fn moves_by_words_across_lines() {
+    let mut buffer = Buffer::new("return a + b;\n}");
+    buffer.move_cursor(Motion::WordForward);
+    assert_eq!(buffer.cursor(), (0, 7));
+    buffer.move_cursor(Motion::WordForward);
+    assert_eq!(buffer.cursor(), (0, 11));
+    buffer.move_cursor(Motion::WordForward);
+    assert_eq!(buffer.cursor(), (1, 0));
+    buffer.move_cursor(Motion::WordBackward);
+    assert_eq!(buffer.cursor(), (0, 13));
+    buffer.move_cursor(Motion::WordBackward);
+    assert_eq!(buffer.cursor(), (0, 11));
+}
+
+#[test]
+fn keeps_the_cursor_inside_the_text() {
+    let mut buffer = Buffer::new("long line\nshort\n");
+    buffer.set_cursor(0, 9);
+    buffer.move_cursor(Motion::Down);
+    assert_eq!(buffer.cursor(), (1, 5));
+
+    buffer.set_text("x");
+    assert_eq!(buffer.cursor(), (0, 1));
+    buffer.set_text("");
+    assert_eq!(buffer.cursor(), (0, 0));
+    assert_eq!(buffer.text(), "\n");
+}
@@ -0,0 +1,118 @@
+use ce::highlight::{tokenize, TokenKind};
+
+/// Tokens of `line` other than whitespace and punctuation.
+fn tokens(line: &str) -> Vec<(TokenKind, &str)> {
+    let tokens = tokenize(line);
+    assert_eq!(
+        tokens.iter().map(|(_, token)| *token).collect::<String>(),
+        line
+    );
+    tokens
+        .into_iter()
+        .filter(|(kind, _)| *kind != TokenKind::Text)
+        .collect()
+}
+
+#[test]
+fn x86_intel() {
+    assert_eq!(
+        tokens("        mov     eax, DWORD PTR [rbp-4]"),
+        vec![
+            (TokenKind::Mnemonic, "mov"),
+            (TokenKind::Register, "eax"),
+            (TokenKind::Register, "rbp"),
+            (TokenKind::Immediate, "-4"),
+        ]
+    );
+    assert_eq!(
+        tokens("        call    puts@PLT"),
+        vec![(TokenKind::Mnemonic, "call")]
+    );
+}
+
+#[test]
+fn x86_att() {
+    assert_eq!(
+        tokens("        movl    $42, %eax  # answer"),
+        vec![
+            (TokenKind::Mnemonic, "movl"),
+            (TokenKind::Immediate, "$42"),
+            (TokenKind::Register, "%eax"),
+            (TokenKind::Comment, "# answer"),
+        ]
+    );
+    assert_eq!(
+        tokens("        jmp     .L3"),
+        vec![(TokenKind::Mnemonic, "jmp"), (TokenKind::Label, ".L3")]
+    );
+}
+
+#[test]
+fn arm() {
+    assert_eq!(
+        tokens("        add     w0, w0, #1  // increment"),
+        vec![
+            (TokenKind::Mnemonic, "add"),
+            (TokenKind::Register, "w0"),
+            (TokenKind::Register, "w0"),
+            (TokenKind::Immediate, "#1"),
+            (TokenKind::Comment, "// increment"),
+        ]
+    );
+    assert_eq!(
+        tokens("        ldr     r3, [sp, #4] @ spill"),
+        vec![
+            (TokenKind::Mnemonic, "ldr"),
+            (TokenKind::Register, "r3"),
+            (TokenKind::Register, "sp"),
+            (TokenKind::Immediate, "#4"),
+            (TokenKind::Comment, "@ spill"),
+        ]
+    );
+}
+
+#[test]
+fn risc_v() {
+    assert_eq!(
+        tokens("        addi    sp, sp, -16"),
+        vec![
+            (TokenKind::Mnemonic, "addi"),
+            (TokenKind::Register, "sp"),
+            (TokenKind::Register, "sp"),
+            (TokenKind::Immediate, "-16"),
+        ]
+    );
+    assert_eq!(
+        tokens("        sw      ra, 12(sp)"),
+        vec![
+            (TokenKind::Mnemonic, "sw"),
+            (TokenKind::Register, "ra"),
+            (TokenKind::Immediate, "12"),
+            (TokenKind::Register, "sp"),
+        ]
+    );
+}
+
+#[test]
+fn labels_and_directives() {
+    assert_eq!(
+        tokens("sum(int*, int):"),
+        vec![(TokenKind::Label, "sum(int*, int):")]
+    );
+    assert_eq!(
+        tokens("        .string \"Hello \\\"world\\\"\""),
+        vec![
+            (TokenKind::Directive, ".string"),
+            (TokenKind::Immediate, "\"Hello \\\"world\\\"\""),
+        ]
+    );
+    assert_eq!(
+        tokens("    1139:  call   1130 <square(int)>"),
+        vec![
+            (TokenKind::Label, "1139:"),
+            (TokenKind::Mnemonic, "call"),
+            (TokenKind::Immediate, "1130"),
+            (TokenKind::Label, "<square(int)>"),
+        ]
+    );
+}
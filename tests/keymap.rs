@@ -0,0 +1,114 @@
+use ce::keymap::{Action, Error, Key, Keymap, Lookup};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use std::collections::HashMap;
+
+fn keys(sequence: &str) -> Vec<Key> {
+    sequence
+        .split_whitespace()
+        .map(|key| key.parse().unwrap())
+        .collect()
+}
+
+#[test]
+fn parses_keys() {
+    assert_eq!(
+        "ctrl-d".parse::<Key>().unwrap(),
+        Key::new(KeyCode::Char('d'), KeyModifiers::CONTROL)
+    );
+    assert_eq!(
+        "shift-g".parse::<Key>().unwrap(),
+        Key::new(KeyCode::Char('G'), KeyModifiers::NONE)
+    );
+    assert_eq!(
+        "PageDown".parse::<Key>().unwrap(),
+        Key::new(KeyCode::PageDown, KeyModifiers::NONE)
+    );
+    assert_eq!(
+        "alt--".parse::<Key>().unwrap(),
+        Key::new(KeyCode::Char('-'), KeyModifiers::ALT)
+    );
+    assert_eq!(
+        "f5".parse::<Key>().unwrap(),
+        Key::new(KeyCode::F(5), KeyModifiers::NONE)
+    );
+    assert_eq!(
+        "hyper-x".parse::<Key>(),
+        Err(Error::InvalidKey("hyper-x".to_owned()))
+    );
+    assert!("f13".parse::<Key>().is_err());
+}
+
+#[test]
+fn formats_keys() {
+    for key in ["ctrl-d", "G", "pagedown", "alt-space", "f1", "shift-up"] {
+        assert_eq!(key.parse::<Key>().unwrap().to_string(), key);
+    }
+}
+
+#[test]
+fn key_events_ignore_shift_on_characters() {
+    let event = KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT);
+    assert_eq!(Key::from(event), "G".parse().unwrap());
+}
+
+#[test]
+fn looks_up_default_bindings() {
+    let keymap = Keymap::default();
+
+    assert_eq!(
+        keymap.lookup(&keys("j")),
+        Lookup::Action(Action::ScrollDown)
+    );
+    assert_eq!(
+        keymap.lookup(&keys("down")),
+        Lookup::Action(Action::ScrollDown)
+    );
+    assert_eq!(keymap.lookup(&keys("q")), Lookup::Action(Action::Quit));
+    assert_eq!(keymap.lookup(&keys("g")), Lookup::Pending);
+    assert_eq!(keymap.lookup(&keys("g g")), Lookup::Action(Action::Top));
+    assert_eq!(keymap.lookup(&keys("g x")), Lookup::None);
+    assert_eq!(keymap.lookup(&keys("ctrl-j")), Lookup::None);
+}
+
+#[test]
+fn overrides_replace_default_bindings() {
+    let overrides = HashMap::from([
+        (Action::Quit, vec!["ctrl-c".to_owned()]),
+        (Action::Top, vec!["j".to_owned()]),
+    ]);
+    let keymap = Keymap::new(&overrides).unwrap();
+
+    assert_eq!(keymap.lookup(&keys("q")), Lookup::None);
+    assert_eq!(keymap.lookup(&keys("ctrl-c")), Lookup::Action(Action::Quit));
+    assert_eq!(keymap.lookup(&keys("j")), Lookup::Action(Action::Top));
+    assert_eq!(keymap.lookup(&keys("g g")), Lookup::None);
+    assert_eq!(
+        keymap.lookup(&keys("down")),
+        Lookup::Action(Action::ScrollDown)
+    );
+}
+
+#[test]
+fn invalid_overrides_name_the_action() {
+    let overrides = HashMap::from([(Action::Yank, vec!["ctrl-".to_owned()])]);
+    match Keymap::new(&overrides).unwrap_err() {
+        Error::InvalidBinding {
+            action, sequence, ..
+        } => {
+            assert_eq!(action, Action::Yank);
+            assert_eq!(sequence, "ctrl-");
+        }
+        e => panic!("Unexpected error {:?}", e),
+    }
+}
+
+#[test]
+fn every_action_has_a_default_binding() {
+    let keymap = Keymap::default();
+
+    for action in Action::ALL {
+        assert!(!keymap.sequences(action).is_empty(), "{:?}", action);
+    }
+    assert_eq!(keymap.sequences(Action::Top), vec!["g g", "home"]);
+}
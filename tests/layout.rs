@@ -0,0 +1,157 @@
+use ce::layout::{neighbor, Direction, Layout, Node, Pane, Side};
+
+use tui::layout::Rect;
+
+fn all(_: Pane) -> bool {
+    true
+}
+
+/// Source on the left, ASM above the outputs on the right.
+fn nested() -> Node {
+    let mut outputs = Node::split(
+        Direction::Vertical,
+        vec![
+            Node::pane(Pane::Asm),
+            Node::pane(Pane::Stdout),
+            Node::pane(Pane::Stderr),
+        ],
+    );
+    outputs.weight = 30;
+    Node::split(
+        Direction::Horizontal,
+        vec![Node::pane(Pane::Source), outputs],
+    )
+}
+
+#[test]
+fn splits_in_proportion_to_weights() {
+    let areas = nested().areas(Rect::new(0, 0, 40, 30), &all);
+
+    assert_eq!(
+        areas,
+        vec![
+            (Pane::Source, Rect::new(0, 0, 10, 30)),
+            (Pane::Asm, Rect::new(10, 0, 30, 10)),
+            (Pane::Stdout, Rect::new(10, 10, 30, 10)),
+            (Pane::Stderr, Rect::new(10, 20, 30, 10)),
+        ]
+    );
+}
+
+#[test]
+fn hidden_panes_give_their_room_to_the_rest() {
+    let areas = nested().areas(Rect::new(0, 0, 40, 30), &|pane| pane != Pane::Stdout);
+
+    assert_eq!(
+        areas,
+        vec![
+            (Pane::Source, Rect::new(0, 0, 10, 30)),
+            (Pane::Asm, Rect::new(10, 0, 30, 15)),
+            (Pane::Stderr, Rect::new(10, 15, 30, 15)),
+        ]
+    );
+
+    // A split without visible panes takes no room at all
+    let areas = nested().areas(Rect::new(0, 0, 40, 30), &|pane| pane == Pane::Source);
+    assert_eq!(areas, vec![(Pane::Source, Rect::new(0, 0, 40, 30))]);
+}
+
+#[test]
+fn resizes_in_the_innermost_shared_split() {
+    let mut layout = nested();
+    assert!(layout.resize(Pane::Asm, 10, &all));
+    assert_eq!(layout.children[1].children[0].weight, 13);
+    assert_eq!(layout.children[1].weight, 30);
+
+    // Alone in its split, the whole split grows instead
+    let mut layout = nested();
+    assert!(layout.resize(Pane::Asm, 10, &|pane| pane != Pane::Stdout
+        && pane != Pane::Stderr));
+    assert_eq!(layout.children[1].children[0].weight, 10);
+    assert_eq!(layout.children[1].weight, 34);
+
+    // Weights stay positive
+    let mut layout = nested();
+    assert!(layout.resize(Pane::Source, -200, &all));
+    assert_eq!(layout.children[0].weight, 1);
+
+    assert!(!layout.resize(Pane::Source, 10, &|pane| pane == Pane::Source));
+}
+
+#[test]
+fn moves_edges_between_neighbors() {
+    let area = Rect::new(0, 0, 40, 30);
+    let mut layout = nested();
+    assert!(layout.move_edge(area, Pane::Source, Side::Right, 20, &all));
+    assert!(layout.move_edge(area, Pane::Stdout, Side::Up, 5, &all));
+
+    assert_eq!(
+        layout.areas(area, &all),
+        vec![
+            (Pane::Source, Rect::new(0, 0, 20, 30)),
+            (Pane::Asm, Rect::new(20, 0, 20, 5)),
+            (Pane::Stdout, Rect::new(20, 5, 20, 15)),
+            (Pane::Stderr, Rect::new(20, 20, 20, 10)),
+        ]
+    );
+
+    // Nothing on that side
+    assert!(!layout.move_edge(area, Pane::Source, Side::Left, 5, &all));
+    assert!(!layout.move_edge(area, Pane::Asm, Side::Up, 5, &all));
+}
+
+#[test]
+fn normalizes_every_pane_into_the_tree_once() {
+    let mut layout = Layout {
+        shown: vec![Pane::Asm, Pane::Asm],
+        root: Node::split(
+            Direction::Vertical,
+            vec![
+                Node::pane(Pane::Asm),
+                Node::pane(Pane::Outline),
+                Node::pane(Pane::Asm),
+            ],
+        ),
+    };
+    layout.normalize();
+
+    assert_eq!(layout.shown, vec![Pane::Asm]);
+    let panes = layout.root.panes();
+    assert_eq!(panes[0], Pane::Asm);
+    assert_eq!(panes.len(), Pane::ALL.len() - 1);
+    assert!(!panes.contains(&Pane::Outline));
+
+    // A single pane becomes a split
+    let mut layout = Layout {
+        shown: vec![],
+        root: Node::pane(Pane::Source),
+    };
+    layout.normalize();
+    assert_eq!(layout.root.panes()[0], Pane::Source);
+    assert_eq!(layout.root.panes().len(), Pane::ALL.len() - 1);
+}
+
+#[test]
+fn finds_neighbors() {
+    let areas = nested().areas(Rect::new(0, 0, 40, 30), &all);
+    let area = |pane| areas.iter().find(|(p, _)| *p == pane).unwrap().1;
+
+    assert_eq!(
+        neighbor(&areas, area(Pane::Source), Side::Right),
+        Some(Pane::Asm)
+    );
+    assert_eq!(neighbor(&areas, area(Pane::Source), Side::Left), None);
+    assert_eq!(
+        neighbor(&areas, area(Pane::Stdout), Side::Left),
+        Some(Pane::Source)
+    );
+    assert_eq!(
+        neighbor(&areas, area(Pane::Stdout), Side::Up),
+        Some(Pane::Asm)
+    );
+    assert_eq!(
+        neighbor(&areas, area(Pane::Stdout), Side::Down),
+        Some(Pane::Stderr)
+    );
+    assert_eq!(neighbor(&areas, area(Pane::Stderr), Side::Down), None);
+}
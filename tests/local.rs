@@ -0,0 +1,381 @@
+use ce::backend::Backend;
+use ce::compiler_explorer::{CompileOptions, Filters, RemarkKind};
+use ce::local::{instruction_set, parse_asm, parse_objdump, parse_remarks, LocalCompiler};
+
+use std::path::Path;
+
+const GCC_OUTPUT: &str = r#"	.file	"example.cpp"
+	.intel_syntax noprefix
+	.text
+.Ltext0:
+	.file 0 "/home/user" "/tmp/example.cpp"
+	.globl	_Z1fi
+	.type	_Z1fi, @function
+_Z1fi:
+.LVL0:
+.LFB0:
+	.file 1 "/tmp/example.cpp"
+	.loc 1 1 13 view -0
+	.cfi_startproc
+	mov	eax, edi
+	.loc 1 2 28 view .LVU3
+	test	edi, edi
+	jle	.L2
+	mov	edx, 0
+.L3:
+	.loc 1 3 36 is_stmt 1 discriminator 3 view .LVU5
+	add	eax, edx
+	# loop latch
+	cmp	ecx, 1
+	jg	.L3
+.L2:
+	.loc 1 4 42 is_stmt 1 discriminator 4 view .LVU10
+	ret
+	.cfi_endproc
+.LFE0:
+	.size	_Z1fi, .-_Z1fi
+	.section	.rodata.str1.1,"aMS",@progbits,1
+.LC0:
+	.string	"hello"
+	.section	.debug_info,"",@progbits
+.Ldebug_info0:
+	.long	0xbf
+	.quad	.LVL0
+"#;
+
+fn texts(filters: &Filters) -> Vec<String> {
+    parse_asm(GCC_OUTPUT, filters, Path::new("/tmp/example.cpp"))
+        .into_iter()
+        .map(|asm| asm.text)
+        .collect()
+}
+
+#[test]
+fn filters_directives_labels_and_comments() {
+    assert_eq!(
+        texts(&Filters::default()),
+        vec![
+            "_Z1fi:",
+            "        mov     eax, edi",
+            "        test    edi, edi",
+            "        jle     .L2",
+            "        mov     edx, 0",
+            ".L3:",
+            "        add     eax, edx",
+            "        cmp     ecx, 1",
+            "        jg      .L3",
+            ".L2:",
+            "        ret",
+            "        .string \"hello\"",
+        ]
+    );
+}
+
+#[test]
+fn keeps_everything_without_filters() {
+    let filters = Filters {
+        labels: false,
+        directives: false,
+        comment_only: false,
+        ..Default::default()
+    };
+    let texts = texts(&filters);
+
+    assert_eq!(texts.len(), GCC_OUTPUT.lines().count());
+    assert!(texts.contains(&".LVL0:".to_owned()));
+    assert!(texts.contains(&"        # loop latch".to_owned()));
+}
+
+#[test]
+fn maps_loc_directives_to_source_lines() {
+    let asm = parse_asm(
+        GCC_OUTPUT,
+        &Filters::default(),
+        Path::new("/tmp/example.cpp"),
+    );
+    let lines: Vec<_> = asm.iter().map(|asm| asm.source.clone()).collect();
+
+    assert_eq!(
+        lines,
+        vec![
+            None,
+            Some((None, 1)),
+            Some((None, 2)),
+            Some((None, 2)),
+            Some((None, 2)),
+            None,
+            Some((None, 3)),
+            Some((None, 3)),
+            Some((None, 3)),
+            None,
+            Some((None, 4)),
+            None,
+        ]
+    );
+}
+
+#[test]
+fn other_files_keep_their_name() {
+    let asm = parse_asm(GCC_OUTPUT, &Filters::default(), Path::new("/tmp/other.cpp"));
+
+    assert_eq!(
+        asm[1].source,
+        Some((Some("/tmp/example.cpp".to_owned()), 1))
+    );
+}
+
+const OBJDUMP_OUTPUT: &str = "
+/tmp/example.out:     file format elf64-x86-64
+
+
+Disassembly of section .text:
+
+0000000000001040 <_start>:
+_start():
+    1040:\t31 ed                \txor    ebp,ebp
+    1042:\t49 89 d1             \tmov    r9,rdx
+
+0000000000001139 <main>:
+main():
+/tmp/example.cpp:28
+    1139:\t48 83 ec 08          \tsub    rsp,0x8
+/tmp/example.cpp:31 (discriminator 1)
+    113d:\t48 8d 3d c0 0e 00 00 \tlea    rdi,[rip+0xec0]        # 2004 <_IO_stdin_used+0x4>
+\t\t\t7: R_X86_64_PC32\t.LC0-0x4
+    1144:\t48 b8 01 02 03 04 05 \tmovabs rax,0x807060504030201
+    114b:\t06 07 08 
+    114e:\tc3                   \tret
+";
+
+#[test]
+fn parses_objdump_columns() {
+    let asm = parse_objdump(
+        OBJDUMP_OUTPUT,
+        &Filters::default(),
+        Path::new("/tmp/example.cpp"),
+    );
+    let texts: Vec<_> = asm.iter().map(|asm| asm.text.as_str()).collect();
+
+    assert_eq!(
+        texts,
+        vec![
+            "main:",
+            "        sub    rsp,0x8",
+            "        lea    rdi,[rip+0xec0]        # 2004 <_IO_stdin_used+0x4>",
+            "        7: R_X86_64_PC32 .LC0-0x4",
+            "        movabs rax,0x807060504030201",
+            "        ret",
+        ]
+    );
+    assert_eq!(asm[0].address, None);
+    assert_eq!(asm[1].address, Some(0x1139));
+    assert_eq!(asm[1].opcodes, vec!["48", "83", "ec", "08"]);
+    assert_eq!(asm[1].source, Some((None, 28)));
+    assert_eq!(asm[2].source, Some((None, 31)));
+    assert_eq!(asm[3].address, None);
+    assert_eq!(asm[4].opcodes.len(), 10);
+    assert_eq!(asm[5].address, Some(0x114e));
+}
+
+#[test]
+fn objdump_keeps_library_code_without_filter() {
+    let filters = Filters {
+        library_code: false,
+        ..Default::default()
+    };
+    let asm = parse_objdump(OBJDUMP_OUTPUT, &filters, Path::new("/tmp/example.cpp"));
+
+    assert_eq!(asm[0].text, "_start:");
+    assert_eq!(asm[1].address, Some(0x1040));
+    assert_eq!(asm[1].source, None);
+}
+
+#[test]
+fn parses_gcc_remarks() {
+    let output =
+        "/tmp/example.cpp:4:42: optimized:  Inlining int square(int)/0 into int sum(int*, int)/1.
+Unit growth for small function inlining: 15->15 (0%)
+/tmp/example.cpp:4:21: missed: couldn't vectorize loop
+/tmp/example.cpp:2:5: note: vectorized 0 loops in function.
+/usr/include/c++/12/bits/stl_vector.h:10:1: missed: not inlinable
+";
+    let remarks = parse_remarks(output, Path::new("/tmp/example.cpp"));
+
+    assert_eq!(remarks.len(), 3);
+    assert_eq!(remarks[0].kind, RemarkKind::Passed);
+    assert_eq!(remarks[0].location, Some((4, 42)));
+    assert_eq!(
+        remarks[0].message,
+        "Inlining int square(int)/0 into int sum(int*, int)/1."
+    );
+    assert_eq!(remarks[1].kind, RemarkKind::Missed);
+    assert_eq!(remarks[2].kind, RemarkKind::Analysis);
+    assert!(remarks.iter().all(|remark| remark.pass.is_empty()));
+}
+
+#[test]
+fn parses_clang_remarks() {
+    let output = "/tmp/example.cpp:7:10: remark: 'square' inlined into 'main' with (cost=-35, threshold=337) at callsite main:1:10; [-Rpass=inline]
+/tmp/example.cpp:3:3: remark: loop not vectorized [-Rpass-missed=loop-vectorize]
+/tmp/example.cpp:3:3: remark: the cost-model indicates that interleaving is not beneficial [-Rpass-analysis=loop-vectorize]
+";
+    let remarks = parse_remarks(output, Path::new("/tmp/example.cpp"));
+
+    assert_eq!(remarks.len(), 3);
+    assert_eq!(remarks[0].kind, RemarkKind::Passed);
+    assert_eq!(remarks[0].pass, "inline");
+    assert_eq!(
+        remarks[0].message,
+        "'square' inlined into 'main' with (cost=-35, threshold=337) at callsite main:1:10;"
+    );
+    assert_eq!(remarks[1].kind, RemarkKind::Missed);
+    assert_eq!(remarks[1].pass, "loop-vectorize");
+    assert_eq!(remarks[1].location, Some((3, 3)));
+    assert_eq!(remarks[2].kind, RemarkKind::Analysis);
+}
+
+fn has_compiler(compiler: &str) -> bool {
+    std::process::Command::new(compiler)
+        .arg("--version")
+        .output()
+        .is_ok()
+}
+
+#[tokio::test]
+async fn compile_and_execute_with_local_toolchain() {
+    if !has_compiler("c++") {
+        eprintln!("No local c++ compiler, skipping");
+        return;
+    }
+
+    let options = CompileOptions {
+        arguments: vec!["-O1".to_owned()],
+        execute: true,
+        ..Default::default()
+    };
+    let source = "#include <cstdio>\nint square(int x) {\n  return x * x;\n}\nint main() {\n  puts(\"Hi there!!\");\n  return square(4);\n}\n";
+
+    let result = LocalCompiler::new("cpp")
+        .compile("c++", source, &options)
+        .await
+        .unwrap();
+
+    assert_eq!(result.code, 0);
+    let square = result
+        .asm
+        .iter()
+        .position(|asm| asm.text == "square(int):")
+        .expect("demangled label for square");
+    assert_eq!(result.asm[square + 1].source, Some((None, 3)));
+
+    let execution = result.execution.unwrap();
+    assert_eq!(execution.code, 16);
+    assert_eq!(execution.stdout[0].text, "Hi there!!");
+}
+
+#[tokio::test]
+async fn disassemble_object_with_local_toolchain() {
+    if !has_compiler("c++") || !has_compiler("objdump") {
+        eprintln!("No local c++ compiler or objdump, skipping");
+        return;
+    }
+
+    let options = CompileOptions {
+        filters: Filters {
+            binary_object: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let result = LocalCompiler::new("cpp")
+        .compile("c++", "int square(int x) {\n  return x * x;\n}\n", &options)
+        .await
+        .unwrap();
+
+    assert_eq!(result.code, 0);
+    assert_eq!(result.asm[0].text, "square(int):");
+    assert_eq!(result.asm[1].address, Some(0));
+    assert!(!result.asm[1].opcodes.is_empty());
+    assert!(result.asm.iter().any(|asm| asm.source == Some((None, 2))));
+}
+
+#[tokio::test]
+async fn local_compile_errors_are_tagged() {
+    if !has_compiler("c++") {
+        eprintln!("No local c++ compiler, skipping");
+        return;
+    }
+
+    let result = LocalCompiler::new("cpp")
+        .compile(
+            "c++",
+            "int main() {\n  return x;\n}\n",
+            &CompileOptions::default(),
+        )
+        .await
+        .unwrap();
+
+    assert_ne!(result.code, 0);
+    assert_eq!(result.asm[0].text, "<Compilation failed>");
+    let error = result
+        .stderr
+        .iter()
+        .find_map(|line| line.tag.clone())
+        .expect("a tagged diagnostic");
+    assert_eq!(error.0, 2);
+    assert!(error.1.contains("'x'"), "{}", error.1);
+}
+
+#[tokio::test]
+async fn missing_compiler() {
+    let result = LocalCompiler::new("cpp")
+        .compile(
+            "/does/not/exist/cc",
+            "int main() {}",
+            &CompileOptions::default(),
+        )
+        .await;
+
+    assert!(matches!(
+        result,
+        Err(ce::compiler_explorer::Error::Process(_, _))
+    ));
+}
+
+#[test]
+fn maps_target_triples_to_instruction_sets() {
+    assert_eq!(instruction_set("x86_64-linux-gnu\n"), Some("amd64"));
+    assert_eq!(
+        instruction_set("aarch64-apple-darwin23.0.0"),
+        Some("aarch64")
+    );
+    assert_eq!(instruction_set("armv7a-none-eabi"), Some("arm32"));
+    assert_eq!(instruction_set("riscv64-unknown-elf"), None);
+}
+
+#[tokio::test]
+async fn disassembles_with_the_configured_objdump() {
+    if !has_compiler("c++") {
+        eprintln!("No local c++ compiler, skipping");
+        return;
+    }
+
+    let options = CompileOptions {
+        filters: Filters {
+            binary_object: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let result = LocalCompiler::new("cpp")
+        .with_objdump("/does/not/exist/objdump")
+        .compile("c++", "int square(int x) {\n  return x * x;\n}\n", &options)
+        .await;
+
+    assert!(matches!(
+        result,
+        Err(ce::compiler_explorer::Error::Process(program, _)) if program == "/does/not/exist/objdump"
+    ));
+}
@@ -0,0 +1,399 @@
+//! Offline stand-in for a compiler explorer instance.
+//!
+//! The behaviour of `/api/compiler/{id}/compile` is selected by the compiler id, which lets every
+//! test exercise a different code path against the same server:
+//!
+//! - `success`: compiles and, for executor requests, runs the program. Binary requests get
+//!   addresses and opcodes. Outputs asked for with the `produce*` compiler options are included
+//! - `compile_error`: the compiler rejects the source
+//! - `internal_error`: always HTTP 500 with a plain text body
+//! - `malformed_json`: HTTP 200 with a body that is not JSON
+//! - `missing_code`: valid JSON without the `code` field
+//! - `invalid_asm`: asm entries without `text`
+//! - `flaky`: HTTP 503 for the first two requests, then behaves like `success`
+//! - `rate_limited`: always HTTP 429
+//!
+//! `/api/asm/amd64/{opcode}` documents `mov` only, other instruction sets are unknown.
+//! `/api/compilers` lists `success` and `compile_error`, `/api/languages` lists C++ and C, and
+//! `/api/shortener` answers every session with the same link.
+#![allow(dead_code)]
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde_json::{json, Value};
+
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+pub const FLAKY_FAILURES: usize = 2;
+
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: Method,
+    pub path: String,
+    pub body: Option<Value>,
+}
+
+#[derive(Default)]
+struct State {
+    requests: Vec<RecordedRequest>,
+    flaky_failures: usize,
+}
+
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    shutdown: Option<tokio::sync::oneshot::Sender<()>>,
+}
+
+impl MockServer {
+    pub async fn start() -> Self {
+        let state = Arc::new(Mutex::new(State::default()));
+
+        let service_state = state.clone();
+        let make_service = make_service_fn(move |_| {
+            let state = service_state.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    handle_request(state.clone(), request)
+                }))
+            }
+        });
+
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service);
+        let addr = server.local_addr();
+
+        let (shutdown, shutdown_rx) = tokio::sync::oneshot::channel();
+        tokio::spawn(server.with_graceful_shutdown(async {
+            let _ = shutdown_rx.await;
+        }));
+
+        Self {
+            addr,
+            state,
+            shutdown: Some(shutdown),
+        }
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+async fn handle_request(
+    state: Arc<Mutex<State>>,
+    request: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    let method = request.method().clone();
+    let path = request.uri().path().to_owned();
+    let body = hyper::body::to_bytes(request.into_body())
+        .await
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<Value>(&bytes).ok());
+
+    state.lock().unwrap().requests.push(RecordedRequest {
+        method: method.clone(),
+        path: path.clone(),
+        body: body.clone(),
+    });
+
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let response = match (&method, &segments[..]) {
+        (&Method::GET, ["api", "compilers"]) => json_response(compilers()),
+        (&Method::GET, ["api", "languages"]) => json_response(languages()),
+        (&Method::GET, ["api", "asm", "amd64", opcode]) => json_response(asm_docs(opcode)),
+        (&Method::GET, ["api", "asm", _, _]) => {
+            text_response(StatusCode::NOT_FOUND, "Unknown instruction set")
+        }
+        (&Method::POST, ["api", "shortener"]) => {
+            json_response(json!({ "url": "https://godbolt.org/z/mock" }))
+        }
+        (&Method::POST, ["api", "compiler", compiler, "compile"]) => {
+            let option = |pointer: &str| {
+                body.as_ref()
+                    .and_then(|body| body.pointer(pointer))
+                    .and_then(Value::as_bool)
+                    .unwrap_or(false)
+            };
+            let execute = option("/options/compilerOptions/executorRequest");
+            let binary = option("/options/filters/binary");
+            let mut response = compile(&state, compiler, execute, binary);
+            if *compiler == "success" && !execute {
+                let compiler_options = body
+                    .as_ref()
+                    .and_then(|body| body.pointer("/options/compilerOptions"))
+                    .filter(|options| options.as_object().is_some_and(|o| !o.is_empty()));
+                if let Some(compiler_options) = compiler_options {
+                    response = json_response(add_produced_outputs(
+                        compilation_success(),
+                        compiler_options,
+                    ));
+                }
+            }
+            response
+        }
+        _ => text_response(StatusCode::NOT_FOUND, "Not found"),
+    };
+    Ok(response)
+}
+
+fn compile(state: &Mutex<State>, compiler: &str, execute: bool, binary: bool) -> Response<Body> {
+    match compiler {
+        "success" if execute => json_response(execution_success()),
+        "success" if binary => json_response(binary_success()),
+        "success" => json_response(compilation_success()),
+        "compile_error" => json_response(compilation_error()),
+        "internal_error" => {
+            let mut response = text_response(StatusCode::INTERNAL_SERVER_ERROR, "Internal error");
+            // Server errors are retried, keep the test fast
+            response
+                .headers_mut()
+                .insert("Retry-After", "0".parse().unwrap());
+            response
+        }
+        "malformed_json" => text_response(StatusCode::OK, "{\"code\": 0, \"asm\": ["),
+        "missing_code" => json_response(json!({ "asm": [], "stdout": [], "stderr": [] })),
+        "invalid_asm" => json_response(json!({
+            "code": 0,
+            "asm": [{ "source": null }],
+            "stdout": [],
+            "stderr": [],
+        })),
+        "flaky" => {
+            let mut state = state.lock().unwrap();
+            if state.flaky_failures < FLAKY_FAILURES {
+                state.flaky_failures += 1;
+                let mut response =
+                    text_response(StatusCode::SERVICE_UNAVAILABLE, "Service unavailable");
+                response
+                    .headers_mut()
+                    .insert("Retry-After", "0".parse().unwrap());
+                response
+            } else {
+                json_response(compilation_success())
+            }
+        }
+        "rate_limited" => {
+            let mut response = text_response(StatusCode::TOO_MANY_REQUESTS, "Slow down");
+            response
+                .headers_mut()
+                .insert("Retry-After", "0".parse().unwrap());
+            response
+        }
+        _ => text_response(
+            StatusCode::NOT_FOUND,
+            &format!("Unknown compiler {}", compiler),
+        ),
+    }
+}
+
+fn json_response(value: Value) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "application/json")
+        .body(Body::from(value.to_string()))
+        .unwrap()
+}
+
+fn text_response(status: StatusCode, text: &str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "text/plain")
+        .body(Body::from(text.to_owned()))
+        .unwrap()
+}
+
+fn compilation_success() -> Value {
+    json!({
+        "code": 0,
+        "stdout": [],
+        "stderr": [],
+        "asm": [
+            { "text": "main:", "source": null },
+            { "text": "        push    rbp", "source": { "file": null, "line": 29 } },
+            { "text": "        mov     eax, 32", "source": { "file": null, "line": 32 } },
+            { "text": "        pop     rbp", "source": { "file": null, "line": 32 } },
+            { "text": "        ret", "source": { "file": null, "line": 32 } },
+        ],
+    })
+}
+
+fn binary_success() -> Value {
+    json!({
+        "code": 0,
+        "stdout": [],
+        "stderr": [],
+        "asm": [
+            { "text": "main:", "source": null },
+            {
+                "text": " push rbp",
+                "source": { "file": null, "line": 29 },
+                "address": 4464,
+                "opcodes": ["55"],
+            },
+            {
+                "text": " mov eax,0x20",
+                "source": { "file": null, "line": 32 },
+                "address": 4465,
+                "opcodes": ["b8", "20", "00", "00", "00"],
+            },
+        ],
+    })
+}
+
+fn add_produced_outputs(mut result: Value, compiler_options: &Value) -> Value {
+    let requested = |option: &str| !compiler_options[option].is_null();
+    if requested("produceIr") {
+        // Newer instances wrap the IR in an object
+        result["irOutput"] = json!({
+            "asm": [
+                { "text": "define dso_local noundef i32 @main() {" },
+                { "text": "  ret i32 32", "source": { "file": null, "line": 32 } },
+                { "text": "}" },
+            ],
+        });
+    }
+    if requested("produceAst") {
+        result["astOutput"] = json!([
+            { "text": "TranslationUnitDecl" },
+            { "text": "`-FunctionDecl <line:1:1, col:25> main 'int ()'" },
+        ]);
+    }
+    if requested("produceGccDump") {
+        let passes = json!([
+            { "name": "original (tree)", "command_prefix": "-fdump-tree-original", "filename_suffix": "t.original" },
+            { "name": "optimized (tree)", "command_prefix": "-fdump-tree-optimized", "filename_suffix": "t.optimized" },
+        ]);
+        let selected = &compiler_options["produceGccDump"]["pass"];
+        result["gccDumpOutput"] = json!({
+            "all": passes,
+            "selectedPass": selected,
+            "currentPassOutput": if selected.is_null() {
+                "<No pass selected>".to_owned()
+            } else {
+                format!("Dump of {}", selected["name"].as_str().unwrap_or_default())
+            },
+        });
+    }
+    if requested("produceOptPipeline") {
+        result["optPipelineOutput"] = json!({
+            "results": {
+                "main": [
+                    {
+                        "name": "SROAPass on main",
+                        "machine": false,
+                        "irChanged": true,
+                        "before": [{ "text": "  %1 = alloca i32" }, { "text": "  ret i32 32" }],
+                        "after": [{ "text": "  ret i32 32" }],
+                    },
+                    {
+                        "name": "EarlyCSEPass on main",
+                        "machine": false,
+                        "irChanged": false,
+                        "before": [{ "text": "  ret i32 32" }],
+                        "after": [{ "text": "  ret i32 32" }],
+                    },
+                ],
+            },
+        });
+    }
+    if requested("produceOptInfo") {
+        result["optOutput"] = json!([
+            {
+                "Pass": "inline",
+                "Name": "Inlined",
+                "DebugLoc": { "File": "example.cpp", "Line": 7, "Column": 10 },
+                "Function": "main",
+                "Args": [{ "Callee": "square" }, { "String": " inlined into " }, { "Caller": "main" }],
+                "optType": "Passed",
+                "displayString": "square inlined into main",
+            },
+            {
+                "Pass": "loop-vectorize",
+                "Name": "MissedDetails",
+                "DebugLoc": { "File": "example.cpp", "Line": 3, "Column": 5 },
+                "Function": "sum",
+                "Args": [{ "String": "loop not vectorized" }],
+                "optType": "Missed",
+            },
+        ]);
+    }
+    result
+}
+
+fn compilation_error() -> Value {
+    json!({
+        "code": 1,
+        "stdout": [],
+        "stderr": [
+            {
+                "text": "<source>:3:1: error: expected ';' after top level declarator",
+                "tag": { "line": 3, "column": 1, "text": "expected ';' after top level declarator" },
+            },
+            { "text": "1 error generated." },
+        ],
+        "asm": [{ "text": "<Compilation failed>" }],
+    })
+}
+
+fn execution_success() -> Value {
+    json!({
+        "code": 32,
+        "stdout": [{ "text": "Hi there!!" }],
+        "stderr": [],
+        "didExecute": true,
+    })
+}
+
+fn compilers() -> Value {
+    json!([
+        {
+            "id": "success",
+            "name": "mock clang",
+            "lang": "c++",
+            "instructionSet": "amd64",
+        },
+        {
+            "id": "compile_error",
+            "name": "mock broken clang",
+            "lang": "c++",
+            "instructionSet": "amd64",
+        },
+    ])
+}
+
+fn asm_docs(opcode: &str) -> Value {
+    if opcode != "mov" {
+        return json!({ "found": false });
+    }
+    json!({
+        "found": true,
+        "result": {
+            "tooltip": "Copies the second operand to the first operand.",
+            "html": "<p>Copies the second operand (source operand) to the first operand \
+                     (destination operand).</p><p>Both operands must be the same size &amp; \
+                     <b>cannot</b> both be memory locations.</p>",
+            "url": "https://www.felixcloutier.com/x86/mov",
+        },
+    })
+}
+
+fn languages() -> Value {
+    json!([
+        { "id": "c++", "name": "C++", "extensions": [".cpp", ".cxx", ".h", ".hpp"] },
+        { "id": "c", "name": "C", "extensions": [".c", ".h"] },
+    ])
+}
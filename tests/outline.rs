@@ -0,0 +1,52 @@
+mod common;
+
+use ce::outline::{parse, Symbol};
+use common::asm;
+
+#[test]
+fn lists_functions_with_their_instructions() {
+    let symbols = parse(&asm(&[
+        ".LC0:",
+        "        .string \"hello\"",
+        "square(int):",
+        "        imul    edi, edi",
+        "        mov     eax, edi",
+        "        ret",
+        "main:",
+        "        xor     eax, eax",
+        ".L2:",
+        "        .p2align 4",
+        "        ret",
+    ]));
+
+    assert_eq!(
+        symbols,
+        vec![
+            Symbol {
+                name: "square(int)".to_owned(),
+                line: 2,
+                end: 6,
+                instructions: 3,
+            },
+            Symbol {
+                name: "main".to_owned(),
+                line: 6,
+                end: 11,
+                instructions: 2,
+            },
+        ]
+    );
+}
+
+#[test]
+fn demangles_labels() {
+    let symbols = parse(&asm(&[
+        "_Z6squarei:",
+        "        ret",
+        "_Zbogus:",
+        "        ret",
+    ]));
+
+    assert_eq!(symbols[0].name, "square(int)");
+    assert_eq!(symbols[1].name, "_Zbogus");
+}